@@ -0,0 +1,254 @@
+use crate::*;
+
+use crate::config::ONE_NEAR;
+use crate::events::MilestonesUpdatedData;
+use crate::template::escape_html;
+use near_contract_standards::non_fungible_token::core::{NonFungibleTokenCore, NonFungibleTokenResolver};
+use near_contract_standards::non_fungible_token::events::NftMint;
+use near_contract_standards::non_fungible_token::metadata::{
+    NFTContractMetadata, NonFungibleTokenMetadataProvider, TokenMetadata, NFT_METADATA_SPEC,
+};
+use near_contract_standards::non_fungible_token::{Token, TokenId};
+use near_sdk::PromiseOrValue;
+
+/// Maximal number of milestones the owner can define
+const MAX_MILESTONES: usize = 20;
+
+/// Counter of `UserStats` a milestone is reached by
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum MilestoneKind {
+    /// Number of tasks completed on time
+    CompletedOnTime,
+    /// Longest series of tasks completed on time in a row
+    LongestStreak,
+    /// Deposits refunded for tasks completed on time, in yoctoNear
+    TotalRecovered,
+}
+
+/// Achievement that is minted as an NFT to every user whose stats reach the threshold
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Milestone {
+    /// Uniq id of the milestone, a part of ids of its tokens
+    pub id: String,
+    pub title: String,
+    pub description: String,
+    pub kind: MilestoneKind,
+    pub threshold: U128,
+    /// When true, tokens of the milestone can not be transferred
+    pub soulbound: bool,
+}
+
+impl Milestone {
+    fn is_reached(&self, stats: &UserStats) -> bool {
+        let value = match self.kind {
+            MilestoneKind::CompletedOnTime => stats.completed_on_time as Balance,
+            MilestoneKind::LongestStreak => stats.longest_streak as Balance,
+            MilestoneKind::TotalRecovered => stats.total_refunded.0,
+        };
+        value >= self.threshold.0
+    }
+
+    /// Id of the token of the milestone minted to the user
+    fn token_id(&self, account_id: &AccountId) -> TokenId {
+        format!("{}:{}", self.id, account_id)
+    }
+}
+
+pub(crate) fn default_milestones() -> Vec<Milestone> {
+    vec![
+        Milestone {
+            id: "on-time-10".to_string(),
+            title: "Ten on time".to_string(),
+            description: "Completed 10 tasks before their deadlines".to_string(),
+            kind: MilestoneKind::CompletedOnTime,
+            threshold: U128(10),
+            soulbound: true,
+        },
+        Milestone {
+            id: "streak-30".to_string(),
+            title: "Unbroken".to_string(),
+            description: "Completed 30 tasks on time in a row".to_string(),
+            kind: MilestoneKind::LongestStreak,
+            threshold: U128(30),
+            soulbound: true,
+        },
+        Milestone {
+            id: "recovered-100".to_string(),
+            title: "Hundred back".to_string(),
+            description: "Recovered 100 NEAR of deposits".to_string(),
+            kind: MilestoneKind::TotalRecovered,
+            threshold: U128(100 * ONE_NEAR),
+            soulbound: false,
+        },
+    ]
+}
+
+/// Renders the image of an achievement token, the same token always gets the same image
+pub(crate) fn achievement_svg(title: &str, owner_id: &AccountId) -> String {
+    format!(
+        concat!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="400" height="400" viewBox="0 0 400 400">"#,
+            r##"<rect width="400" height="400" rx="24" fill="#1b1b3a"/>"##,
+            r##"<circle cx="200" cy="150" r="80" fill="none" stroke="#f5c542" stroke-width="12"/>"##,
+            r##"<text x="200" y="170" font-size="56" text-anchor="middle" fill="#f5c542">&#9733;</text>"##,
+            r##"<text x="200" y="290" font-family="sans-serif" font-size="28" text-anchor="middle" fill="#ffffff">{}</text>"##,
+            r##"<text x="200" y="330" font-family="sans-serif" font-size="16" text-anchor="middle" fill="#b0b0d0">{}</text>"##,
+            "</svg>"
+        ),
+        escape_html(title),
+        escape_html(owner_id.as_str())
+    )
+}
+
+impl Contract {
+    /// Mints tokens of the milestones the user has reached since the last check
+    pub(crate) fn award_achievements(&mut self, account_id: &AccountId, user_records: &mut UserRecords) {
+        let reached: Vec<Milestone> = self
+            .milestones
+            .iter()
+            .filter(|milestone| {
+                !user_records.achievements.contains(&milestone.id) && milestone.is_reached(&user_records.stats)
+            })
+            .cloned()
+            .collect();
+
+        for milestone in reached {
+            let token_id = milestone.token_id(account_id);
+            let media_hash = env::sha256(achievement_svg(&milestone.title, account_id).as_bytes());
+            let metadata = TokenMetadata {
+                title: Some(milestone.title.clone()),
+                description: Some(milestone.description.clone()),
+                media: Some(format!("nft/{}.svg", token_id)),
+                media_hash: Some(media_hash.into()),
+                copies: None,
+                issued_at: Some(env::block_timestamp_ms().to_string()),
+                expires_at: None,
+                starts_at: None,
+                updated_at: None,
+                extra: None,
+                reference: None,
+                reference_hash: None,
+            };
+            // The storage of achievements is paid by the service
+            self.nft
+                .internal_mint_with_refund(token_id.clone(), account_id.clone(), Some(metadata), None);
+            if milestone.soulbound {
+                self.soulbound_tokens.insert(&token_id);
+            }
+            user_records.achievements.push(milestone.id);
+
+            NftMint {
+                owner_id: account_id,
+                token_ids: &[&token_id],
+                memo: None,
+            }
+            .emit();
+        }
+    }
+
+    fn assert_transferable(&self, token_id: &TokenId) {
+        assert!(
+            !self.soulbound_tokens.contains(token_id),
+            "Achievement is soulbound and can not be transferred"
+        );
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Returns the milestones achievements are minted for
+    pub fn get_milestones(&self) -> Vec<Milestone> {
+        self.milestones.clone()
+    }
+
+    /// Replaces the milestones, tokens that were already minted are kept.
+    /// Can be called only by the owner
+    pub fn set_milestones(&mut self, milestones: Vec<Milestone>) {
+        self.assert_owner();
+        assert!(
+            milestones.len() <= MAX_MILESTONES,
+            "At most {} milestones can be defined",
+            MAX_MILESTONES
+        );
+        for (index, milestone) in milestones.iter().enumerate() {
+            assert!(
+                !milestone.id.is_empty() && !milestone.id.contains(':'),
+                "Milestone id must be non-empty and can not contain ':'"
+            );
+            assert!(
+                milestones[..index].iter().all(|other| other.id != milestone.id),
+                "Milestone ids must be unique"
+            );
+        }
+        self.milestones = milestones.clone();
+
+        Event::MilestonesUpdated(vec![MilestonesUpdatedData { milestones }]).emit();
+    }
+}
+
+#[near_bindgen]
+impl NonFungibleTokenCore for Contract {
+    #[payable]
+    fn nft_transfer(
+        &mut self,
+        receiver_id: AccountId,
+        token_id: TokenId,
+        approval_id: Option<u64>,
+        memo: Option<String>,
+    ) {
+        self.assert_transferable(&token_id);
+        self.nft.nft_transfer(receiver_id, token_id, approval_id, memo)
+    }
+
+    #[payable]
+    fn nft_transfer_call(
+        &mut self,
+        receiver_id: AccountId,
+        token_id: TokenId,
+        approval_id: Option<u64>,
+        memo: Option<String>,
+        msg: String,
+    ) -> PromiseOrValue<bool> {
+        self.assert_transferable(&token_id);
+        self.nft.nft_transfer_call(receiver_id, token_id, approval_id, memo, msg)
+    }
+
+    fn nft_token(&self, token_id: TokenId) -> Option<Token> {
+        self.nft.nft_token(token_id)
+    }
+}
+
+#[near_bindgen]
+impl NonFungibleTokenResolver for Contract {
+    #[private]
+    fn nft_resolve_transfer(
+        &mut self,
+        previous_owner_id: AccountId,
+        receiver_id: AccountId,
+        token_id: TokenId,
+        approved_account_ids: Option<HashMap<AccountId, u64>>,
+    ) -> bool {
+        self.nft
+            .nft_resolve_transfer(previous_owner_id, receiver_id, token_id, approved_account_ids)
+    }
+}
+
+near_contract_standards::impl_non_fungible_token_enumeration!(Contract, nft);
+
+#[near_bindgen]
+impl NonFungibleTokenMetadataProvider for Contract {
+    fn nft_metadata(&self) -> NFTContractMetadata {
+        NFTContractMetadata {
+            spec: NFT_METADATA_SPEC.to_string(),
+            name: "Dear Procrastination achievements".to_string(),
+            symbol: "DEARPRO".to_string(),
+            icon: None,
+            // Media of tokens is served by web4 of the contract
+            base_uri: Some(format!("https://{}.page", env::current_account_id())),
+            reference: None,
+            reference_hash: None,
+        }
+    }
+}
@@ -0,0 +1,146 @@
+use crate::*;
+
+use crate::web4::{TasksQuery, Web4Response};
+use near_contract_standards::non_fungible_token::enumeration::NonFungibleTokenEnumeration;
+
+/// Task as it is returned by the JSON API, amounts and timestamps are strings
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ApiTask {
+    pub account_id: AccountId,
+    pub record_id: i64,
+    pub task: String,
+    pub is_complete: bool,
+    /// Deposit in yoctoNEAR or in the smallest units of `token_id`
+    pub deposit: U128,
+    pub deposit_status: DepositStatus,
+    /// Fungible token the deposit was paid in, `null` for NEAR
+    pub token_id: Option<AccountId>,
+    /// Deadline, in nanoseconds
+    pub deadline_time: U64,
+    /// Time of creation, in nanoseconds
+    pub created_at: U64,
+    pub is_overdue: bool,
+    pub verifier: Option<AccountId>,
+    pub beneficiary: Option<AccountId>,
+    pub recurring_id: Option<i64>,
+    pub proofs: Vec<ApiProof>,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ApiProof {
+    pub uri: String,
+    pub hash: String,
+    /// Time of submission, in nanoseconds
+    pub submitted_at: U64,
+}
+
+impl ApiTask {
+    fn new(account_id: &AccountId, record_id: i64, record: Record) -> Self {
+        Self {
+            account_id: account_id.clone(),
+            record_id,
+            is_overdue: record.is_overdue(),
+            task: record.task,
+            is_complete: record.is_complete_status,
+            deposit: U128(record.guarantee_of_task_completion),
+            deposit_status: record.deposit_status,
+            token_id: record.token_id,
+            deadline_time: U64(record.deadline_time),
+            created_at: U64(record.created_at),
+            verifier: record.verifier,
+            beneficiary: record.beneficiary,
+            recurring_id: record.recurring_id,
+            proofs: record
+                .proofs
+                .into_iter()
+                .map(|proof| ApiProof {
+                    uri: proof.uri,
+                    hash: proof.hash,
+                    submitted_at: U64(proof.submitted_at),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Page of tasks of a user
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ApiTaskPage {
+    pub account_id: AccountId,
+    /// `all`, `open`, `completed` or `overdue`
+    pub status: String,
+    pub page: u64,
+    pub limit: u64,
+    /// Number of tasks with the status on all pages, counted as by `get_user_task_count`
+    pub total: u64,
+    pub tasks: Vec<ApiTask>,
+}
+
+/// Counters of the whole service
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ApiStats {
+    pub paused: bool,
+    /// Ledger of NEAR deposits
+    pub treasury: TreasuryStats,
+    pub challenges: u64,
+    /// Number of minted achievement tokens
+    pub achievements: U128,
+}
+
+/// Body of an error response
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ApiError {
+    pub error: String,
+}
+
+impl Contract {
+    /// `/api/users/{account}/tasks.json`
+    pub(crate) fn api_user_tasks(&self, account_id: AccountId, query: &TasksQuery) -> Web4Response {
+        let filter = query.status.filter();
+        let tasks = self
+            .get_user_tasks(
+                account_id.clone(),
+                Some(query.first_index()),
+                Some(query.limit),
+                Some(filter.clone()),
+            )
+            .into_iter()
+            .map(|(record_id, record)| ApiTask::new(&account_id, record_id, record))
+            .collect();
+
+        Web4Response::json_response(&ApiTaskPage {
+            total: self.get_user_task_count(account_id.clone(), Some(filter)),
+            account_id,
+            status: query.status.as_str().to_string(),
+            page: query.page,
+            limit: query.limit,
+            tasks,
+        })
+    }
+
+    /// `/api/users/{account}/stats.json`
+    pub(crate) fn api_user_stats(&self, account_id: AccountId) -> Web4Response {
+        Web4Response::json_response(&self.get_user_stats(account_id))
+    }
+
+    /// `/api/tasks/{account}/{id}.json`
+    pub(crate) fn api_task(&self, account_id: AccountId, record_id: i64) -> Option<Web4Response> {
+        let record = self.get_user_records(&account_id)?.get_record(record_id)?;
+        Some(Web4Response::json_response(&ApiTask::new(&account_id, record_id, record)))
+    }
+
+    /// `/api/stats.json`
+    pub(crate) fn api_stats(&self) -> Web4Response {
+        Web4Response::json_response(&ApiStats {
+            paused: self.paused,
+            treasury: self.get_treasury_stats(),
+            challenges: self.challenges.len(),
+            achievements: self.nft_total_supply(),
+        })
+    }
+}
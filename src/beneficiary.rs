@@ -0,0 +1,125 @@
+use crate::*;
+
+use crate::events::{DonationData, Event};
+use crate::token::{GAS_FOR_FT_TRANSFER, GAS_FOR_FT_TRANSFER_CALLBACK};
+use crate::utils::bps_share;
+use near_contract_standards::fungible_token::core::ext_ft_core;
+use near_sdk::{is_promise_success, Gas, ONE_YOCTO};
+
+const GAS_FOR_DONATION_CALLBACK: Gas = Gas(10_000_000_000_000);
+/// Gas of the transfers to beneficiaries started by one call
+const GAS_FOR_DONATIONS: Gas = Gas(200_000_000_000_000);
+
+/// Maximal number of transfers to beneficiaries started by one call,
+/// a transfer of a token with its callback takes the most gas
+pub(crate) const MAX_DONATIONS_PER_CALL: usize =
+    (GAS_FOR_DONATIONS.0 / (GAS_FOR_FT_TRANSFER.0 + GAS_FOR_FT_TRANSFER_CALLBACK.0)) as usize;
+
+/// Part of a withheld deposit that is sent to the beneficiary of the task
+pub(crate) struct Donation {
+    pub beneficiary_id: AccountId,
+    pub token_id: Option<AccountId>,
+    pub amount: Balance,
+}
+
+impl Donation {
+    /// Checks whether both donations can be sent with one transfer
+    pub(crate) fn is_paid_with(&self, other: &Donation) -> bool {
+        self.beneficiary_id == other.beneficiary_id && self.token_id == other.token_id
+    }
+}
+
+impl Contract {
+    /// Checks that the account can receive withheld deposits of the user's tasks
+    pub(crate) fn assert_valid_beneficiary(&self, account_id: &AccountId, beneficiary: &AccountId) {
+        assert!(beneficiary != account_id, "Beneficiary of the task can not be its owner");
+        assert!(
+            !self.config.beneficiary_whitelist_only || self.config.charities.contains(beneficiary),
+            "Beneficiary must be one of the charities of the config"
+        );
+    }
+
+    /// Takes the part of the withheld deposit of the record that goes to its beneficiary,
+    /// the keeper fee and the platform fee stay in the service. The donation is sent
+    /// by `send_donations`
+    pub(crate) fn withheld_donation(
+        &mut self,
+        account_id: &AccountId,
+        record_id: i64,
+        record: &Record,
+        keeper_fee: Balance,
+    ) -> Option<Donation> {
+        let beneficiary = record.beneficiary.clone()?;
+        let platform_fee =
+            bps_share(record.guarantee_of_task_completion, self.config.platform_fee_bps);
+        let amount = record
+            .guarantee_of_task_completion
+            .saturating_sub(keeper_fee)
+            .saturating_sub(platform_fee);
+        if amount == 0 {
+            return None;
+        }
+
+        self.update_treasury(record.token_id.as_ref(), |treasury| treasury.total_donated += amount);
+
+        Event::DepositDonated(vec![DonationData {
+            account_id: account_id.clone(),
+            record_id,
+            beneficiary_id: beneficiary.clone(),
+            amount: U128(amount),
+            platform_fee: U128(platform_fee),
+            token_id: record.token_id.clone(),
+        }])
+        .emit();
+
+        Some(Donation {
+            beneficiary_id: beneficiary,
+            token_id: record.token_id.clone(),
+            amount,
+        })
+    }
+
+    /// Sends donations with one transfer per beneficiary and token
+    pub(crate) fn send_donations<I: IntoIterator<Item = Donation>>(&mut self, donations: I) {
+        let mut transfers: Vec<Donation> = Vec::new();
+        for donation in donations {
+            match transfers.iter_mut().find(|transfer| transfer.is_paid_with(&donation)) {
+                Some(transfer) => transfer.amount += donation.amount,
+                None => transfers.push(donation),
+            }
+        }
+
+        for Donation { beneficiary_id, token_id, amount } in transfers {
+            let transfer = match &token_id {
+                None => Promise::new(beneficiary_id).transfer(amount),
+                Some(token_id) => ext_ft_core::ext(token_id.clone())
+                    .with_attached_deposit(ONE_YOCTO)
+                    .with_static_gas(GAS_FOR_FT_TRANSFER)
+                    .ft_transfer(beneficiary_id, U128(amount), None),
+            };
+            let callback_gas = match token_id {
+                None => GAS_FOR_DONATION_CALLBACK,
+                Some(_) => GAS_FOR_FT_TRANSFER_CALLBACK,
+            };
+            transfer.then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(callback_gas)
+                    .on_donation_complete(token_id, U128(amount)),
+            );
+        }
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Returns the donation to the withheld deposits of the service if the transfer failed,
+    /// so the owner can withdraw it
+    #[private]
+    pub fn on_donation_complete(&mut self, token_id: Option<AccountId>, amount: U128) -> bool {
+        if !is_promise_success() {
+            self.update_treasury(token_id.as_ref(), |treasury| treasury.total_donated -= amount.0);
+            return false;
+        }
+        true
+    }
+}
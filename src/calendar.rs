@@ -0,0 +1,156 @@
+use crate::*;
+
+use crate::config::ONE_NEAR;
+use crate::deadline::format_utc_basic;
+use crate::web4::Web4Response;
+
+/// Maximal length of a content line without the line break, in octets
+const MAX_LINE_LENGTH: usize = 75;
+/// Maximal number of tasks in the feed, the latest ones are shown
+pub(crate) const MAX_CALENDAR_TASKS: usize = 100;
+
+/// Escapes a TEXT value as RFC 5545 requires
+fn escape_text(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for char in text.chars() {
+        match char {
+            '\\' => escaped.push_str("\\\\"),
+            ';' => escaped.push_str("\\;"),
+            ',' => escaped.push_str("\\,"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => {}
+            _ => escaped.push(char),
+        }
+    }
+    escaped
+}
+
+/// Splits the line into lines of at most 75 octets, continuation lines start with a space.
+/// Characters are never split
+fn fold_line(line: &str, output: &mut String) {
+    let mut length = 0;
+    for char in line.chars() {
+        if length + char.len_utf8() > MAX_LINE_LENGTH {
+            output.push_str("\r\n ");
+            length = 1;
+        }
+        output.push(char);
+        length += char.len_utf8();
+    }
+    output.push_str("\r\n");
+}
+
+/// Formats a deposit in yoctoNEAR as NEAR, e.g. "1.5 NEAR"
+fn format_near(amount: Balance) -> String {
+    let fraction = amount % ONE_NEAR;
+    if fraction == 0 {
+        return format!("{} NEAR", amount / ONE_NEAR);
+    }
+    let fraction = format!("{:024}", fraction);
+    format!("{}.{} NEAR", amount / ONE_NEAR, fraction.trim_end_matches('0'))
+}
+
+/// Checks whether the task belongs to the feed: it is not done yet or its deposit is not settled
+fn is_open(record: &Record) -> bool {
+    !record.is_complete_status || record.deposit_status == DepositStatus::Contributed
+}
+
+/// Status of the to-do: cancelled if the deposit was withheld or the task was cancelled,
+/// otherwise still to be done
+fn todo_status(record: &Record) -> &'static str {
+    match record.deposit_status {
+        DepositStatus::Withheld | DepositStatus::Cancelled => "CANCELLED",
+        DepositStatus::Contributed | DepositStatus::Refunded => "NEEDS-ACTION",
+    }
+}
+
+impl Contract {
+    /// Renders open tasks of the account as an iCalendar feed, every task is a `VTODO`
+    /// due at its deadline with the stake in the description. Only the latest
+    /// `MAX_CALENDAR_TASKS` tasks are rendered, the newest first
+    pub(crate) fn calendar_feed(&self, account_id: &AccountId) -> Web4Response {
+        let contract_id = env::current_account_id();
+        let now = format_utc_basic(env::block_timestamp());
+
+        let mut lines = vec![
+            "BEGIN:VCALENDAR".to_string(),
+            "VERSION:2.0".to_string(),
+            format!("PRODID:-//Dear Procrastination//{}//EN", contract_id),
+            "CALSCALE:GREGORIAN".to_string(),
+            format!("X-WR-CALNAME:{}", escape_text(&format!("Tasks of {}", account_id))),
+            "REFRESH-INTERVAL;VALUE=DURATION:PT1H".to_string(),
+            "X-PUBLISHED-TTL:PT1H".to_string(),
+        ];
+        let records: Vec<(i64, Record)> = match self.get_user_records(account_id) {
+            Some(user_records) => (1..user_records.record_id)
+                .rev()
+                .filter_map(|record_id| Some((record_id, user_records.get_record(record_id)?)))
+                .filter(|(_, record)| is_open(record))
+                .take(MAX_CALENDAR_TASKS)
+                .collect(),
+            None => Vec::new(),
+        };
+        for (record_id, record) in records {
+            let stake = match &record.token_id {
+                None => format_near(record.guarantee_of_task_completion),
+                Some(token_id) => format!("{} of {}", record.guarantee_of_task_completion, token_id),
+            };
+            let description = format!("Stake: {}\nDeposit: {:?}", stake, record.deposit_status);
+            lines.extend([
+                "BEGIN:VTODO".to_string(),
+                format!("UID:{}-{}@{}", record_id, account_id, contract_id),
+                format!("DTSTAMP:{}", now),
+                format!("CREATED:{}", format_utc_basic(record.created_at)),
+                format!("DUE:{}", format_utc_basic(record.deadline_time)),
+                format!("SUMMARY:{}", escape_text(&record.task)),
+                format!("DESCRIPTION:{}", escape_text(&description)),
+                format!("STATUS:{}", todo_status(&record)),
+                format!("URL:https://{}.page/u/{}/task/{}", contract_id, account_id, record_id),
+                "END:VTODO".to_string(),
+            ]);
+        }
+        lines.push("END:VCALENDAR".to_string());
+
+        let mut calendar = String::new();
+        for line in &lines {
+            fold_line(line, &mut calendar);
+        }
+        Web4Response::calendar_response(calendar)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_escaping_of_text() {
+        assert_eq!(
+            escape_text("Write a post; then, maybe\r\na \\ talk"),
+            "Write a post\\; then\\, maybe\\na \\\\ talk"
+        );
+    }
+
+    #[test]
+    fn check_folding_of_lines() {
+        let mut output = String::new();
+        fold_line(&format!("SUMMARY:{}", "a".repeat(80)), &mut output);
+        let lines: Vec<&str> = output.split("\r\n").collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0].len(), 75);
+        assert_eq!(lines[1], format!(" {}", "a".repeat(13)));
+        assert_eq!(lines[2], "");
+
+        // A character of several octets is moved to the next line as a whole
+        let mut output = String::new();
+        fold_line(&format!("{}é", "a".repeat(74)), &mut output);
+        assert_eq!(output, format!("{}\r\n é\r\n", "a".repeat(74)));
+    }
+
+    #[test]
+    fn check_formatting_of_stakes() {
+        assert_eq!(format_near(3 * ONE_NEAR), "3 NEAR");
+        assert_eq!(format_near(ONE_NEAR + ONE_NEAR / 2), "1.5 NEAR");
+        assert_eq!(format_near(1), "0.000000000000000000000001 NEAR");
+    }
+}
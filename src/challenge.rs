@@ -0,0 +1,404 @@
+use crate::*;
+
+use crate::events::{
+    ChallengeCompletedData, ChallengeCompletionResolvedData, ChallengeCreatedData,
+    ChallengeJoinedData, ChallengePayoutClaimedData, ChallengeSettledData, Event,
+};
+use crate::utils::bps_share;
+use crate::verification::{ClaimStatus, CompletionClaim, VerificationOutcome};
+
+/// Maximal number of participants of a challenge
+const MAX_CHALLENGE_PARTICIPANTS: usize = 100;
+/// Number of challenges read by `get_challenges` if `limit` is not set
+const DEFAULT_CHALLENGES_LIMIT: u64 = 20;
+/// Maximal number of challenges read by `get_challenges`
+const MAX_CHALLENGES_LIMIT: u64 = 100;
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Participant {
+    pub account_id: AccountId,
+    /// Time the participant paid the stake, in nanoseconds
+    pub joined_at: Timestamp,
+    /// Completion submitted to the verifier of the challenge, `None` until then
+    pub completion_claim: Option<CompletionClaim>,
+    /// True when the participant claimed the payout of the settled challenge
+    pub is_paid: bool,
+}
+
+impl Participant {
+    fn is_approved(&self) -> bool {
+        matches!(&self.completion_claim, Some(claim) if claim.status == ClaimStatus::Approved)
+    }
+}
+
+/// A task done by a group, every participant pays the same stake. At settlement the stakes
+/// of participants who failed are split among those whose completion was confirmed
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Challenge {
+    pub creator_id: AccountId,
+    /// Description of the task every participant has to do
+    pub task: String,
+    pub deadline_time: Timestamp,
+    /// Deposit paid by every participant, in yoctoNear
+    pub stake: U128,
+    /// Account that confirms completion of participants, the creator if it is not set
+    pub verifier: Option<AccountId>,
+    /// Participants in the order they joined
+    pub participants: Vec<Participant>,
+    /// True when the payouts were calculated
+    pub is_settled: bool,
+    /// Amount every participant with confirmed completion can claim after settlement, in yoctoNear
+    pub payout: U128,
+}
+
+impl Challenge {
+    fn participant_mut(&mut self, account_id: &AccountId) -> Option<&mut Participant> {
+        self.participants
+            .iter_mut()
+            .find(|participant| &participant.account_id == account_id)
+    }
+
+    fn has_participant(&self, account_id: &AccountId) -> bool {
+        self.participants
+            .iter()
+            .any(|participant| &participant.account_id == account_id)
+    }
+
+    /// Account that approves or rejects completion claims of participants
+    fn verifier_id(&self) -> &AccountId {
+        self.verifier.as_ref().unwrap_or(&self.creator_id)
+    }
+}
+
+impl Contract {
+    /// Approves or rejects the pending completion claim of the participant
+    fn resolve_challenge_claim(
+        &mut self,
+        challenge_id: i64,
+        account_id: AccountId,
+        outcome: VerificationOutcome,
+    ) {
+        let mut challenge = self.challenges.get(&challenge_id).expect("Challenge not found");
+        assert_eq!(
+            challenge.verifier_id(),
+            &env::predecessor_account_id(),
+            "Only the verifier of the challenge can call this method"
+        );
+        assert!(!challenge.is_settled, "Challenge is already settled");
+        let verifier_id = challenge.verifier_id().clone();
+        let claim = challenge
+            .participant_mut(&account_id)
+            .expect("Account is not a participant of the challenge")
+            .completion_claim
+            .as_mut()
+            .filter(|claim| claim.status == ClaimStatus::Pending)
+            .expect("Participant has no completion claim to resolve");
+        claim.status = match outcome {
+            VerificationOutcome::Approve => ClaimStatus::Approved,
+            VerificationOutcome::Reject => ClaimStatus::Rejected,
+        };
+        self.challenges.insert(&challenge_id, &challenge);
+
+        Event::ChallengeCompletionResolved(vec![ChallengeCompletionResolvedData {
+            challenge_id,
+            account_id,
+            verifier_id,
+            approved: outcome == VerificationOutcome::Approve,
+            by_timeout: false,
+        }])
+        .emit();
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Creates a challenge that anyone can join by paying the stake before the deadline.
+    /// The creator takes part only after joining it. Completion of participants is confirmed
+    /// by the verifier or by the creator if the verifier is not set. Returns the id of the challenge
+    pub fn create_challenge(
+        &mut self,
+        task: String,
+        deadline_time: Deadline,
+        stake: U128,
+        verifier: Option<AccountId>,
+    ) -> i64 {
+        assert!(!self.paused, "Creation of tasks is paused");
+        assert!(
+            stake.0 >= self.config.min_deposit.0,
+            "For creation task you need pay minimum {} yoctoNEAR",
+            self.config.min_deposit.0
+        );
+        assert!(
+            stake.0 <= self.config.max_deposit.0,
+            "For creation task you can pay maximum {} yoctoNEAR",
+            self.config.max_deposit.0
+        );
+        assert!(
+            task.len() <= self.config.max_task_length as usize,
+            "Task description is longer than {} bytes",
+            self.config.max_task_length
+        );
+        let deadline_time = self.normalize_deadline(&deadline_time);
+
+        let creator_id = env::predecessor_account_id();
+        let challenge = Challenge {
+            creator_id: creator_id.clone(),
+            task,
+            deadline_time,
+            stake,
+            verifier,
+            participants: Vec::new(),
+            is_settled: false,
+            payout: U128(0),
+        };
+        let challenge_id = self.challenge_id;
+        self.challenges.insert(&challenge_id, &challenge);
+        self.challenge_id += 1;
+
+        Event::ChallengeCreated(vec![ChallengeCreatedData {
+            challenge_id,
+            creator_id,
+            deadline_time: U64(deadline_time),
+            stake,
+        }])
+        .emit();
+
+        challenge_id
+    }
+
+    /// Joins the challenge, the attached deposit must be equal to its stake
+    #[payable]
+    pub fn join_challenge(&mut self, challenge_id: i64) {
+        assert!(!self.paused, "Creation of tasks is paused");
+        let mut challenge = self.challenges.get(&challenge_id).expect("Challenge not found");
+        let account_id = env::predecessor_account_id();
+        assert!(
+            env::block_timestamp() < challenge.deadline_time,
+            "Deadline of the challenge has passed"
+        );
+        assert!(
+            &account_id != challenge.verifier_id(),
+            "Verifier of the challenge can not join it"
+        );
+        assert!(
+            !challenge.has_participant(&account_id),
+            "Account has already joined the challenge"
+        );
+        assert!(
+            challenge.participants.len() < MAX_CHALLENGE_PARTICIPANTS,
+            "Challenge can have at most {} participants",
+            MAX_CHALLENGE_PARTICIPANTS
+        );
+        assert_eq!(
+            env::attached_deposit(),
+            challenge.stake.0,
+            "Attached deposit must be {} yoctoNEAR",
+            challenge.stake.0
+        );
+
+        challenge.participants.push(Participant {
+            account_id: account_id.clone(),
+            joined_at: env::block_timestamp(),
+            completion_claim: None,
+            is_paid: false,
+        });
+        self.challenges.insert(&challenge_id, &challenge);
+        self.treasury.total_contributed += challenge.stake.0;
+
+        Event::ChallengeJoined(vec![ChallengeJoinedData {
+            challenge_id,
+            account_id,
+        }])
+        .emit();
+    }
+
+    /// Submits completion of the task of the challenge to its verifier, allowed only
+    /// before the deadline. The participant wins only if the verifier approves the claim
+    pub fn submit_challenge_completion(&mut self, challenge_id: i64, proof: String) {
+        let mut challenge = self.challenges.get(&challenge_id).expect("Challenge not found");
+        let account_id = env::predecessor_account_id();
+        assert!(
+            env::block_timestamp() <= challenge.deadline_time,
+            "Deadline of the challenge has passed"
+        );
+        assert!(
+            challenge.verifier_id() != &account_id,
+            "Verifier of the challenge can not confirm own completion"
+        );
+        assert!(
+            !proof.is_empty() && proof.len() <= self.config.max_task_length as usize,
+            "Proof must be from 1 to {} bytes",
+            self.config.max_task_length
+        );
+        let participant = challenge
+            .participant_mut(&account_id)
+            .expect("Account is not a participant of the challenge");
+        assert!(
+            participant.completion_claim.is_none(),
+            "Completion has already been submitted"
+        );
+        participant.completion_claim = Some(CompletionClaim {
+            proof: proof.clone(),
+            claimed_at: env::block_timestamp(),
+            status: ClaimStatus::Pending,
+        });
+        self.challenges.insert(&challenge_id, &challenge);
+
+        Event::ChallengeCompleted(vec![ChallengeCompletedData {
+            challenge_id,
+            account_id,
+            proof,
+        }])
+        .emit();
+    }
+
+    /// Confirms completion of the participant. Can be called only by the verifier of the challenge
+    pub fn approve_challenge_completion(&mut self, challenge_id: i64, account_id: AccountId) {
+        self.resolve_challenge_claim(challenge_id, account_id, VerificationOutcome::Approve);
+    }
+
+    /// Rejects completion of the participant, the stake is split among the winners.
+    /// Can be called only by the verifier of the challenge
+    pub fn reject_challenge_completion(&mut self, challenge_id: i64, account_id: AccountId) {
+        self.resolve_challenge_claim(challenge_id, account_id, VerificationOutcome::Reject);
+    }
+
+    /// Calculates payouts of the challenge after its deadline, anyone can call the method.
+    /// Claims the verifier did not answer within `verification_timeout` are resolved with
+    /// `verification_default`. Participants with approved completion can claim back their stake
+    /// and an equal share of the stakes of those who failed minus `platform_fee_bps`.
+    /// If nobody completed the task, all stakes are withheld
+    pub fn settle_challenge(&mut self, challenge_id: i64) {
+        let mut challenge = self.challenges.get(&challenge_id).expect("Challenge not found");
+        assert!(!challenge.is_settled, "Challenge is already settled");
+        assert!(
+            env::block_timestamp() > challenge.deadline_time,
+            "Challenge can be settled only after its deadline"
+        );
+
+        let verifier_id = challenge.verifier_id().clone();
+        let mut resolved = Vec::new();
+        for participant in challenge.participants.iter_mut() {
+            if let Some(claim) = participant.completion_claim.as_mut() {
+                if claim.status != ClaimStatus::Pending {
+                    continue;
+                }
+                assert!(
+                    env::block_timestamp() >= claim.claimed_at + self.config.verification_timeout.0,
+                    "Verifier can still answer completion claims of the challenge"
+                );
+                claim.status = match self.config.verification_default {
+                    VerificationOutcome::Approve => ClaimStatus::Approved,
+                    VerificationOutcome::Reject => ClaimStatus::Rejected,
+                };
+                resolved.push(ChallengeCompletionResolvedData {
+                    challenge_id,
+                    account_id: participant.account_id.clone(),
+                    verifier_id: verifier_id.clone(),
+                    approved: claim.status == ClaimStatus::Approved,
+                    by_timeout: true,
+                });
+            }
+        }
+        if !resolved.is_empty() {
+            Event::ChallengeCompletionResolved(resolved).emit();
+        }
+
+        let stake = challenge.stake.0;
+        let winners = challenge
+            .participants
+            .iter()
+            .filter(|participant| participant.is_approved())
+            .count() as Balance;
+        let failed = challenge.participants.len() as Balance - winners;
+        let pot = failed * stake;
+
+        let platform_fee = bps_share(pot, self.config.platform_fee_bps);
+        let (payout, platform_fee) = match (pot - platform_fee).checked_div(winners) {
+            // The remainder of the division stays in the service with the fee
+            Some(share) => (stake + share, pot - share * winners),
+            // Nobody completed the task
+            None => (0, pot),
+        };
+        self.treasury.total_withheld += platform_fee;
+
+        challenge.is_settled = true;
+        challenge.payout = U128(payout);
+        self.challenges.insert(&challenge_id, &challenge);
+
+        Event::ChallengeSettled(vec![ChallengeSettledData {
+            challenge_id,
+            winners: winners as u32,
+            failed: failed as u32,
+            payout: U128(payout),
+            platform_fee: U128(platform_fee),
+        }])
+        .emit();
+    }
+
+    /// Adds the payout of the settled challenge to the claimable refunds of the caller.
+    /// Every winner claims separately, so settlement does not depend on the number of winners
+    pub fn claim_challenge_payout(&mut self, challenge_id: i64) -> U128 {
+        let mut challenge = self.challenges.get(&challenge_id).expect("Challenge not found");
+        assert!(challenge.is_settled, "Challenge is not settled yet");
+        let account_id = env::predecessor_account_id();
+        let payout = challenge.payout;
+        let participant = challenge
+            .participant_mut(&account_id)
+            .expect("Account is not a participant of the challenge");
+        assert!(
+            participant.is_approved(),
+            "Only participants with approved completion get a payout"
+        );
+        assert!(!participant.is_paid, "Payout has already been claimed");
+        participant.is_paid = true;
+        self.challenges.insert(&challenge_id, &challenge);
+
+        self.assert_migrated(&account_id);
+        let mut user_records = self
+            .get_user_records(&account_id)
+            .unwrap_or_else(|| UserRecords::new(&account_id));
+        user_records.add_claimable(None, payout.0);
+        self.set_user_records(&account_id, user_records);
+        self.treasury.total_refunded += payout.0;
+
+        Event::ChallengePayoutClaimed(vec![ChallengePayoutClaimedData {
+            challenge_id,
+            account_id,
+            payout,
+        }])
+        .emit();
+
+        payout
+    }
+
+    /// Returns the challenge by id
+    pub fn get_challenge(&self, challenge_id: i64) -> Option<Challenge> {
+        self.challenges.get(&challenge_id)
+    }
+
+    /// Returns challenges ordered by id, with `participant` only the challenges
+    /// the account has joined. Challenges with ids from `from_index`, 1 by default, are read
+    /// in batches of `limit`, which is `DEFAULT_CHALLENGES_LIMIT` by default and at most
+    /// `MAX_CHALLENGES_LIMIT`, so a page with a participant can have fewer challenges
+    pub fn get_challenges(
+        &self,
+        from_index: Option<u64>,
+        limit: Option<u64>,
+        participant: Option<AccountId>,
+    ) -> Vec<(i64, Challenge)> {
+        let from_id = from_index.unwrap_or(1) as i64;
+        let limit = std::cmp::min(limit.unwrap_or(DEFAULT_CHALLENGES_LIMIT), MAX_CHALLENGES_LIMIT) as i64;
+        (from_id..std::cmp::min(self.challenge_id, from_id.saturating_add(limit)))
+            .filter_map(|challenge_id| {
+                let challenge = self.challenges.get(&challenge_id)?;
+                match &participant {
+                    Some(account_id) if !challenge.has_participant(account_id) => None,
+                    _ => Some((challenge_id, challenge)),
+                }
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,155 @@
+use crate::*;
+
+use crate::events::{ConfigUpdatedData, Event, OwnerUpdatedData, PauseUpdatedData};
+use near_sdk::json_types::{U128, U64};
+
+pub(crate) const ONE_NEAR: Balance = 1_000_000_000_000_000_000_000_000;
+pub(crate) const ONE_DAY: u64 = 86_400_000_000_000;
+
+pub(crate) const DEFAULT_MIN_DEPOSIT: Balance = 3 * ONE_NEAR;
+pub(crate) const DEFAULT_MAX_DEPOSIT: Balance = 1_000 * ONE_NEAR;
+pub(crate) const DEFAULT_MAX_TASK_LENGTH: u32 = 1_000;
+pub(crate) const DEFAULT_MAX_DEADLINE_HORIZON: u64 = 365 * ONE_DAY;
+pub(crate) const DEFAULT_KEEPER_FEE_BPS: u16 = 100;
+pub(crate) const DEFAULT_EXTENSION_FEE_BPS: u16 = 1_000;
+pub(crate) const DEFAULT_CANCEL_COOLDOWN: u64 = ONE_DAY;
+pub(crate) const DEFAULT_CANCEL_FEE_BPS: u16 = 500;
+pub(crate) const DEFAULT_VERIFICATION_TIMEOUT: u64 = 7 * ONE_DAY;
+pub(crate) const DEFAULT_PLATFORM_FEE_BPS: u16 = 500;
+pub(crate) const DEFAULT_LEADERBOARD_MIN_TASKS: u32 = 5;
+
+/// Denominator of amounts set in basis points
+pub(crate) const MAX_BPS: u16 = 10_000;
+
+/// Settings of the service that can be changed by the owner
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Config {
+    /// Minimal deposit for creation of a task, in yoctoNear
+    pub min_deposit: U128,
+    /// Maximal deposit for creation of a task, in yoctoNear
+    pub max_deposit: U128,
+    /// Maximal length of the task description, in bytes
+    pub max_task_length: u32,
+    /// How far from the moment of creation the deadline can be set, in nanoseconds
+    pub max_deadline_horizon: U64,
+    /// Share of a withheld deposit paid to the caller of `settle_expired_tasks`, in basis points
+    pub keeper_fee_bps: u16,
+    /// Whether an extension of the deadline requires an extra deposit or is paid from the deposit
+    pub extension_policy: ExtensionPolicy,
+    /// Extra deposit or penalty for an extension of the deadline, in basis points of the deposit
+    pub extension_fee_bps: u16,
+    /// How long after creation a task can be cancelled, in nanoseconds
+    pub cancel_cooldown: U64,
+    /// Share of the deposit withheld on cancellation of a task, in basis points
+    pub cancel_fee_bps: u16,
+    /// Fungible tokens accepted as deposits with their minimal deposit,
+    /// in the smallest units of the token
+    pub token_min_deposits: HashMap<AccountId, U128>,
+    /// Maximal deposits of the accepted tokens, in the smallest units of the token.
+    /// Deposits of tokens that are not listed here are not capped
+    pub token_max_deposits: HashMap<AccountId, U128>,
+    /// How long the verifier can answer a completion claim, in nanoseconds
+    pub verification_timeout: U64,
+    /// Outcome of a completion claim the verifier did not answer in time
+    pub verification_default: VerificationOutcome,
+    /// Accounts curated by the owner that can receive deposits of failed tasks
+    pub charities: Vec<AccountId>,
+    /// When true, only charities can be beneficiaries of tasks
+    pub beneficiary_whitelist_only: bool,
+    /// Share of a deposit sent to a beneficiary that stays in the service, in basis points
+    pub platform_fee_bps: u16,
+    /// Minimal number of settled tasks of a user listed by the on-time completion rate
+    pub leaderboard_min_tasks: u32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            min_deposit: U128(DEFAULT_MIN_DEPOSIT),
+            max_deposit: U128(DEFAULT_MAX_DEPOSIT),
+            max_task_length: DEFAULT_MAX_TASK_LENGTH,
+            max_deadline_horizon: U64(DEFAULT_MAX_DEADLINE_HORIZON),
+            keeper_fee_bps: DEFAULT_KEEPER_FEE_BPS,
+            extension_policy: ExtensionPolicy::Penalty,
+            extension_fee_bps: DEFAULT_EXTENSION_FEE_BPS,
+            cancel_cooldown: U64(DEFAULT_CANCEL_COOLDOWN),
+            cancel_fee_bps: DEFAULT_CANCEL_FEE_BPS,
+            token_min_deposits: HashMap::new(),
+            token_max_deposits: HashMap::new(),
+            verification_timeout: U64(DEFAULT_VERIFICATION_TIMEOUT),
+            verification_default: VerificationOutcome::Approve,
+            charities: Vec::new(),
+            beneficiary_whitelist_only: false,
+            platform_fee_bps: DEFAULT_PLATFORM_FEE_BPS,
+            leaderboard_min_tasks: DEFAULT_LEADERBOARD_MIN_TASKS,
+        }
+    }
+}
+
+impl Config {
+    pub(crate) fn assert_valid(&self) {
+        assert!(
+            self.min_deposit.0 <= self.max_deposit.0,
+            "Minimal deposit can not be greater than maximal deposit"
+        );
+        for (token_id, max_deposit) in &self.token_max_deposits {
+            let min_deposit = self
+                .token_min_deposits
+                .get(token_id)
+                .expect("Maximal deposit is set for a token that is not accepted");
+            assert!(
+                min_deposit.0 <= max_deposit.0,
+                "Minimal deposit of {} can not be greater than maximal deposit",
+                token_id
+            );
+        }
+        assert!(self.keeper_fee_bps <= MAX_BPS, "Keeper fee can not exceed 100%");
+        assert!(self.extension_fee_bps <= MAX_BPS, "Extension fee can not exceed 100%");
+        assert!(self.cancel_fee_bps <= MAX_BPS, "Cancel fee can not exceed 100%");
+        assert!(self.platform_fee_bps <= MAX_BPS, "Platform fee can not exceed 100%");
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Returns settings of the service
+    pub fn get_config(&self) -> Config {
+        self.config.clone()
+    }
+
+    /// Returns true if creation of new tasks is stopped
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Replaces settings of the service, can be called only by the owner
+    pub fn set_config(&mut self, config: Config) {
+        self.assert_owner();
+        config.assert_valid();
+        self.config = config.clone();
+
+        Event::ConfigUpdated(vec![ConfigUpdatedData { config }]).emit();
+    }
+
+    /// Stops or resumes creation of new tasks, completing tasks is still allowed while paused.
+    /// Can be called only by the owner
+    pub fn set_paused(&mut self, paused: bool) {
+        self.assert_owner();
+        self.paused = paused;
+
+        Event::PauseUpdated(vec![PauseUpdatedData { paused }]).emit();
+    }
+
+    /// Passes the owner role to another account, can be called only by the owner
+    pub fn set_owner(&mut self, owner_id: AccountId) {
+        self.assert_owner();
+        let old_owner_id = std::mem::replace(&mut self.owner_id, owner_id);
+
+        Event::OwnerUpdated(vec![OwnerUpdatedData {
+            old_owner_id,
+            new_owner_id: self.owner_id.clone(),
+        }])
+        .emit();
+    }
+}
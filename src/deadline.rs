@@ -0,0 +1,250 @@
+use crate::*;
+
+use near_sdk::json_types::U64;
+
+pub(crate) const NANOS_IN_SECOND: u64 = 1_000_000_000;
+const NANOS_IN_MILLISECOND: u64 = 1_000_000;
+const SECONDS_IN_DAY: u64 = 86_400;
+
+/// Deadline of a task as it is passed to the contract
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde", untagged)]
+pub enum Deadline {
+    /// Unix timestamp in nanoseconds, the unit of `env::block_timestamp()`
+    Nanoseconds(U64),
+    Format(DeadlineFormat),
+}
+
+/// Human-friendly formats of a deadline, e.g. `{"seconds": 1693818395}`,
+/// `{"iso8601": "2023-09-04T09:06:35Z"}` or `{"relative": "3d"}`
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde", rename_all = "snake_case")]
+pub enum DeadlineFormat {
+    /// Unix timestamp in seconds
+    Seconds(u64),
+    /// Unix timestamp in milliseconds, as returned by `Date.getTime()`
+    Milliseconds(u64),
+    /// Date and time in UTC like "2023-09-04T09:06:35Z", also accepts an offset like "+03:00",
+    /// a time without seconds and a date without time
+    Iso8601(String),
+    /// Duration from the current block like "3d", "12h" or "1w2d", units are w, d, h, m, s
+    Relative(String),
+}
+
+impl Deadline {
+    /// Converts the deadline to nanoseconds, relative deadlines are counted from `now`
+    pub fn to_nanoseconds(&self, now: Timestamp) -> Timestamp {
+        match self {
+            Deadline::Nanoseconds(nanoseconds) => nanoseconds.0,
+            Deadline::Format(DeadlineFormat::Seconds(seconds)) => seconds
+                .checked_mul(NANOS_IN_SECOND)
+                .unwrap_or_else(|| env::panic_str("Deadline is out of range")),
+            Deadline::Format(DeadlineFormat::Milliseconds(milliseconds)) => milliseconds
+                .checked_mul(NANOS_IN_MILLISECOND)
+                .unwrap_or_else(|| env::panic_str("Deadline is out of range")),
+            Deadline::Format(DeadlineFormat::Iso8601(date)) => parse_iso8601(date)
+                .unwrap_or_else(|| env::panic_str("Deadline is not a valid ISO-8601 date")),
+            Deadline::Format(DeadlineFormat::Relative(duration)) => parse_duration(duration)
+                .and_then(|duration| now.checked_add(duration))
+                .unwrap_or_else(|| env::panic_str("Deadline is not a valid duration")),
+        }
+    }
+}
+
+impl Contract {
+    /// Converts the deadline to nanoseconds and checks that it is in the future,
+    /// but not further than the configured horizon
+    pub(crate) fn normalize_deadline(&self, deadline: &Deadline) -> Timestamp {
+        let now = env::block_timestamp();
+        let deadline_time = deadline.to_nanoseconds(now);
+        assert!(deadline_time > now, "Deadline is in the past");
+        assert!(
+            deadline_time - now <= self.config.max_deadline_horizon.0,
+            "Deadline is too far in the future"
+        );
+        deadline_time
+    }
+}
+
+/// Guesses the unit of a deadline stored by the first release, where the web form passed
+/// milliseconds and other clients passed seconds, and converts it to nanoseconds
+pub(crate) fn normalize_legacy_timestamp(timestamp: Timestamp) -> Timestamp {
+    if timestamp < 100_000_000_000 {
+        timestamp * NANOS_IN_SECOND
+    } else if timestamp < 100_000_000_000_000 {
+        timestamp * NANOS_IN_MILLISECOND
+    } else {
+        timestamp
+    }
+}
+
+/// Parses a duration like "1w2d12h30m15s" into nanoseconds
+pub(crate) fn parse_duration(duration: &str) -> Option<u64> {
+    let mut total: u64 = 0;
+    let mut number: Option<u64> = None;
+    for char in duration.trim().chars() {
+        if let Some(digit) = char.to_digit(10) {
+            number = Some(number.unwrap_or(0).checked_mul(10)?.checked_add(digit as u64)?);
+            continue;
+        }
+        let seconds = match char {
+            'w' => 7 * SECONDS_IN_DAY,
+            'd' => SECONDS_IN_DAY,
+            'h' => 3_600,
+            'm' => 60,
+            's' => 1,
+            _ => return None,
+        };
+        total = total.checked_add(number.take()?.checked_mul(seconds)?)?;
+    }
+    if number.is_some() || total == 0 {
+        return None;
+    }
+    total.checked_mul(NANOS_IN_SECOND)
+}
+
+/// Parses a date like "2023-09-04", "2023-09-04T09:06", "2023-09-04T09:06:35.123Z"
+/// or "2023-09-04T09:06:35+03:00" into nanoseconds
+pub(crate) fn parse_iso8601(date: &str) -> Option<u64> {
+    let bytes = date.trim().as_bytes();
+    let number = |from: usize, len: usize| -> Option<u64> {
+        let digits = bytes.get(from..from + len)?;
+        if !digits.iter().all(u8::is_ascii_digit) {
+            return None;
+        }
+        std::str::from_utf8(digits).ok()?.parse().ok()
+    };
+    let separator =
+        |at: usize, expected: &[u8]| matches!(bytes.get(at), Some(b) if expected.contains(b));
+
+    let year = number(0, 4)?;
+    let month = number(5, 2)?;
+    let day = number(8, 2)?;
+    if !separator(4, b"-") || !separator(7, b"-") || !(1..=12).contains(&month) {
+        return None;
+    }
+    if day == 0 || day > days_in_month(year, month) {
+        return None;
+    }
+
+    let mut seconds = days_from_civil(year, month, day)? * SECONDS_IN_DAY;
+    let mut nanoseconds = 0;
+    let mut position = 10;
+
+    if position < bytes.len() {
+        if !separator(position, b"Tt ") {
+            return None;
+        }
+        let hour = number(11, 2)?;
+        let minute = number(14, 2)?;
+        if !separator(13, b":") || hour > 23 || minute > 59 {
+            return None;
+        }
+        seconds += hour * 3_600 + minute * 60;
+        position = 16;
+
+        if separator(position, b":") {
+            let second = number(17, 2)?;
+            if second > 59 {
+                return None;
+            }
+            seconds += second;
+            position = 19;
+
+            if separator(position, b".") {
+                position += 1;
+                let mut scale = NANOS_IN_SECOND;
+                while let Some(digit) = bytes.get(position).filter(|b| b.is_ascii_digit()) {
+                    scale /= 10;
+                    nanoseconds += (digit - b'0') as u64 * scale;
+                    position += 1;
+                }
+            }
+        }
+
+        match bytes.get(position) {
+            None => {}
+            Some(b'Z') | Some(b'z') if position + 1 == bytes.len() => {}
+            Some(sign @ b'+') | Some(sign @ b'-') if position + 6 == bytes.len() => {
+                let offset_hours = number(position + 1, 2)?;
+                let offset_minutes = number(position + 4, 2)?;
+                if !separator(position + 3, b":") || offset_hours > 23 || offset_minutes > 59 {
+                    return None;
+                }
+                let offset = offset_hours * 3_600 + offset_minutes * 60;
+                seconds = if *sign == b'+' {
+                    seconds.checked_sub(offset)?
+                } else {
+                    seconds + offset
+                };
+            }
+            _ => return None,
+        }
+    }
+
+    seconds
+        .checked_mul(NANOS_IN_SECOND)?
+        .checked_add(nanoseconds)
+}
+
+fn is_leap_year(year: u64) -> bool {
+    match (year % 4, year % 100, year % 400) {
+        (_, _, 0) => true,
+        (_, 0, _) => false,
+        (0, _, _) => true,
+        _ => false,
+    }
+}
+
+fn days_in_month(year: u64, month: u64) -> u64 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Number of days since 1970-01-01, dates before it are not supported
+fn days_from_civil(year: u64, month: u64, day: u64) -> Option<u64> {
+    if year < 1970 {
+        return None;
+    }
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year / 400;
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    (era * 146_097 + day_of_era).checked_sub(719_468)
+}
+
+/// Date of the day counted from 1970-01-01 as (year, month, day)
+fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    let days = days + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days - era * 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+/// Formats nanoseconds as a date and time in UTC like "20230904T090635Z",
+/// the basic format of ISO 8601 used by iCalendar
+pub(crate) fn format_utc_basic(timestamp: Timestamp) -> String {
+    let seconds = timestamp / NANOS_IN_SECOND;
+    let (year, month, day) = civil_from_days(seconds / SECONDS_IN_DAY);
+    let second_of_day = seconds % SECONDS_IN_DAY;
+    format!(
+        "{:04}{:02}{:02}T{:02}{:02}{:02}Z",
+        year,
+        month,
+        day,
+        second_of_day / 3_600,
+        second_of_day % 3_600 / 60,
+        second_of_day % 60
+    )
+}
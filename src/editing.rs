@@ -0,0 +1,212 @@
+use crate::*;
+
+use crate::events::{DepositData, Event, TaskCancelledData, TaskUpdatedData};
+use crate::utils::bps_share;
+use near_sdk::json_types::{U128, U64};
+
+/// How the deadline of a task can be moved to a later time
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub enum ExtensionPolicy {
+    /// The user attaches `extension_fee_bps` of the deposit, it is added to the deposit
+    ExtraDeposit,
+    /// `extension_fee_bps` of the deposit is withheld by the service
+    Penalty,
+}
+
+/// A change made to a record after its creation
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct TaskEdit {
+    /// Time of the change, in nanoseconds
+    pub timestamp: Timestamp,
+    pub change: TaskChange,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub enum TaskChange {
+    /// Description of the task was replaced
+    Task { old_task: String },
+    /// Deadline of the task was moved, `fee` is the extra deposit or the penalty of an extension
+    Deadline {
+        old_deadline_time: Timestamp,
+        fee: Balance,
+    },
+    /// The task was cancelled, `refund` is added to the claimable refunds of the user
+    Cancelled { refund: Balance, fee: Balance },
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Changes the description or the deadline of a task that is not completed yet.
+    /// Editing the description and moving the deadline closer are free,
+    /// extending the deadline costs a fee according to the extension policy of the config,
+    /// for tasks backed by a token the fee is always withheld from the deposit
+    #[payable]
+    pub fn update_task(
+        &mut self,
+        record_id: i64,
+        task: Option<String>,
+        deadline_time: Option<Deadline>,
+    ) -> Record {
+        let account_id = env::predecessor_account_id();
+        self.assert_migrated(&account_id);
+        let mut user_records = self.get_user_records(&account_id).expect("User not found");
+        let mut record = user_records.get_record(record_id).expect("Task not found");
+
+        assert!(
+            !record.is_complete_status && record.deposit_status == DepositStatus::Contributed,
+            "Only tasks in progress can be changed"
+        );
+        assert!(
+            record.recurring_id.is_none(),
+            "Occurrences of recurring tasks can not be changed"
+        );
+        assert!(
+            !record.has_pending_claim(),
+            "Completion of the task is waiting for the verifier"
+        );
+        assert!(
+            record.deadline_time > env::block_timestamp(),
+            "Deadline was ended, task can not be changed"
+        );
+
+        let timestamp = env::block_timestamp();
+        let mut required_deposit = 0;
+        let mut extension_fee = 0;
+
+        if let Some(task) = task {
+            assert!(
+                task.len() <= self.config.max_task_length as usize,
+                "Task description is longer than {} bytes",
+                self.config.max_task_length
+            );
+            let old_task = std::mem::replace(&mut record.task, task);
+            record.history.push(TaskEdit {
+                timestamp,
+                change: TaskChange::Task { old_task },
+            });
+        }
+
+        if let Some(deadline_time) = deadline_time {
+            let deadline_time = self.normalize_deadline(&deadline_time);
+            let mut fee = 0;
+            if deadline_time > record.deadline_time {
+                fee = bps_share(record.guarantee_of_task_completion, self.config.extension_fee_bps);
+                // An extra deposit can be attached only in NEAR,
+                // extensions of tasks backed by a token are always paid with a penalty
+                if self.config.extension_policy == ExtensionPolicy::ExtraDeposit
+                    && record.token_id.is_none()
+                {
+                    required_deposit = fee;
+                    record.guarantee_of_task_completion += fee;
+                    user_records.stats.add_staked(None, fee);
+                    self.treasury.total_contributed += fee;
+                } else {
+                    record.guarantee_of_task_completion -= fee;
+                    user_records.stats.add_fee(record.token_id.as_ref(), fee, 0);
+                    self.update_treasury(record.token_id.as_ref(), |treasury| {
+                        treasury.total_withheld += fee
+                    });
+                }
+            }
+            let old_deadline_time = std::mem::replace(&mut record.deadline_time, deadline_time);
+            record.history.push(TaskEdit {
+                timestamp,
+                change: TaskChange::Deadline {
+                    old_deadline_time,
+                    fee,
+                },
+            });
+            extension_fee = fee;
+        }
+
+        assert_eq!(
+            env::attached_deposit(),
+            required_deposit,
+            "Attached deposit must be {} yoctoNEAR",
+            required_deposit
+        );
+
+        user_records.set_record(record_id, &record);
+        self.set_user_records(&account_id, user_records);
+
+        Event::TaskUpdated(vec![TaskUpdatedData {
+            account_id,
+            record_id,
+            deadline_time: U64(record.deadline_time),
+            extension_fee: U128(extension_fee),
+        }])
+        .emit();
+
+        record
+    }
+
+    /// Cancels a task that is not completed yet. Within the cancel cooldown after creation
+    /// the deposit minus the cancel fee is added to the claimable refunds of the user,
+    /// the fee stays in the service. Returns the refunded amount
+    pub fn cancel_task(&mut self, record_id: i64) -> U128 {
+        let account_id = env::predecessor_account_id();
+        self.assert_migrated(&account_id);
+        let mut user_records = self.get_user_records(&account_id).expect("User not found");
+        let mut record = user_records.get_record(record_id).expect("Task not found");
+
+        assert!(
+            !record.is_complete_status && record.deposit_status == DepositStatus::Contributed,
+            "Only tasks in progress can be cancelled"
+        );
+        assert!(
+            record.recurring_id.is_none(),
+            "Occurrences of recurring tasks can not be cancelled"
+        );
+        assert!(
+            !record.has_pending_claim(),
+            "Completion of the task is waiting for the verifier"
+        );
+        assert!(
+            record.deadline_time > env::block_timestamp(),
+            "Deadline was ended, task can not be cancelled"
+        );
+        assert!(
+            env::block_timestamp() - record.created_at <= self.config.cancel_cooldown.0,
+            "Task can be cancelled only within {} nanoseconds after creation",
+            self.config.cancel_cooldown.0
+        );
+
+        let fee = bps_share(record.guarantee_of_task_completion, self.config.cancel_fee_bps);
+        let refund = record.guarantee_of_task_completion - fee;
+
+        record.deposit_status = DepositStatus::Cancelled;
+        record.history.push(TaskEdit {
+            timestamp: env::block_timestamp(),
+            change: TaskChange::Cancelled { refund, fee },
+        });
+        user_records.set_record(record_id, &record);
+
+        user_records.add_claimable(record.token_id.as_ref(), refund);
+        user_records.stats.add_fee(record.token_id.as_ref(), fee, refund);
+        self.set_user_records(&account_id, user_records);
+        self.update_treasury(record.token_id.as_ref(), |treasury| {
+            treasury.total_refunded += refund;
+            treasury.total_withheld += fee;
+        });
+
+        Event::TaskCancelled(vec![TaskCancelledData {
+            account_id: account_id.clone(),
+            record_id,
+            refund: U128(refund),
+            fee: U128(fee),
+        }])
+        .emit();
+        Event::DepositRefunded(vec![DepositData {
+            account_id,
+            record_id,
+            amount: U128(refund),
+            token_id: record.token_id,
+        }])
+        .emit();
+
+        U128(refund)
+    }
+}
@@ -0,0 +1,383 @@
+use crate::*;
+
+use near_sdk::serde_json;
+
+const EVENT_STANDARD: &str = "dear_procrastination";
+const EVENT_STANDARD_VERSION: &str = "1.0.0";
+
+/// Events of the contract in the format of NEP-297, every event carries a list of changes
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde", tag = "event", content = "data", rename_all = "snake_case")]
+pub enum Event {
+    TaskCreated(Vec<TaskCreatedData>),
+    RecurringTaskCreated(Vec<RecurringTaskCreatedData>),
+    TaskCompleted(Vec<TaskCompletedData>),
+    TaskUpdated(Vec<TaskUpdatedData>),
+    TaskCancelled(Vec<TaskCancelledData>),
+    DepositRefunded(Vec<DepositData>),
+    DepositWithheld(Vec<DepositData>),
+    DepositDonated(Vec<DonationData>),
+    TaskSettled(Vec<TaskSettledData>),
+    LegacyRecordDisputed(Vec<LegacyRecordDisputedData>),
+    ChallengeCreated(Vec<ChallengeCreatedData>),
+    ChallengeJoined(Vec<ChallengeJoinedData>),
+    ChallengeCompleted(Vec<ChallengeCompletedData>),
+    ChallengeCompletionResolved(Vec<ChallengeCompletionResolvedData>),
+    ChallengeSettled(Vec<ChallengeSettledData>),
+    ChallengePayoutClaimed(Vec<ChallengePayoutClaimedData>),
+    ProofSubmitted(Vec<ProofSubmittedData>),
+    CompletionClaimed(Vec<CompletionClaimedData>),
+    CompletionResolved(Vec<CompletionResolvedData>),
+    ConfigUpdated(Vec<ConfigUpdatedData>),
+    MilestonesUpdated(Vec<MilestonesUpdatedData>),
+    PauseUpdated(Vec<PauseUpdatedData>),
+    OwnerUpdated(Vec<OwnerUpdatedData>),
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct TaskCreatedData {
+    pub account_id: AccountId,
+    pub record_id: i64,
+    pub deadline_time: U64,
+    pub deposit: U128,
+    /// Fungible token the deposit was paid in, omitted for NEAR
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_id: Option<AccountId>,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RecurringTaskCreatedData {
+    pub account_id: AccountId,
+    pub recurring_id: i64,
+    pub schedule: Schedule,
+    pub occurrences: u32,
+    /// Deposits of all occurrences paid up front
+    pub deposit: U128,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct TaskCompletedData {
+    pub account_id: AccountId,
+    pub record_id: i64,
+    /// False if the task was completed after the deadline
+    pub on_time: bool,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct TaskUpdatedData {
+    pub account_id: AccountId,
+    pub record_id: i64,
+    /// Deadline of the task after the change
+    pub deadline_time: U64,
+    /// Extra deposit or penalty paid for extension of the deadline
+    pub extension_fee: U128,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct TaskCancelledData {
+    pub account_id: AccountId,
+    pub record_id: i64,
+    pub refund: U128,
+    pub fee: U128,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct DepositData {
+    pub account_id: AccountId,
+    pub record_id: i64,
+    pub amount: U128,
+    /// Fungible token the deposit was paid in, omitted for NEAR
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_id: Option<AccountId>,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct DonationData {
+    pub account_id: AccountId,
+    pub record_id: i64,
+    pub beneficiary_id: AccountId,
+    /// Part of the withheld deposit sent to the beneficiary
+    pub amount: U128,
+    /// Part of the withheld deposit that stayed in the service
+    pub platform_fee: U128,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_id: Option<AccountId>,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct TaskSettledData {
+    pub account_id: AccountId,
+    pub record_id: i64,
+    /// Account that called `settle_expired_tasks`
+    pub keeper_id: AccountId,
+    pub keeper_fee: U128,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct LegacyRecordDisputedData {
+    /// Account that was migrated without the record
+    pub account_id: AccountId,
+    pub record_id: i64,
+    /// Account that got the record when it was migrated earlier
+    pub claimed_by: AccountId,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ChallengeCreatedData {
+    pub challenge_id: i64,
+    pub creator_id: AccountId,
+    pub deadline_time: U64,
+    pub stake: U128,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ChallengeJoinedData {
+    pub challenge_id: i64,
+    pub account_id: AccountId,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ChallengeCompletedData {
+    pub challenge_id: i64,
+    pub account_id: AccountId,
+    pub proof: String,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ChallengeCompletionResolvedData {
+    pub challenge_id: i64,
+    pub account_id: AccountId,
+    pub verifier_id: AccountId,
+    pub approved: bool,
+    /// True if the claim was resolved with the default outcome because the verifier was silent
+    pub by_timeout: bool,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ChallengeSettledData {
+    pub challenge_id: i64,
+    /// Number of participants who completed the task
+    pub winners: u32,
+    /// Number of participants who failed
+    pub failed: u32,
+    /// Amount every winner can claim
+    pub payout: U128,
+    /// Part of the stakes of failed participants that stayed in the service
+    pub platform_fee: U128,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ChallengePayoutClaimedData {
+    pub challenge_id: i64,
+    pub account_id: AccountId,
+    pub payout: U128,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ProofSubmittedData {
+    pub account_id: AccountId,
+    pub record_id: i64,
+    pub proof_uri: String,
+    pub proof_hash: String,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct CompletionClaimedData {
+    pub account_id: AccountId,
+    pub record_id: i64,
+    pub verifier_id: AccountId,
+    pub proof: String,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct CompletionResolvedData {
+    pub account_id: AccountId,
+    pub record_id: i64,
+    pub verifier_id: AccountId,
+    pub approved: bool,
+    /// True if the claim was resolved with the default outcome because the verifier was silent
+    pub by_timeout: bool,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ConfigUpdatedData {
+    pub config: Config,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct MilestonesUpdatedData {
+    pub milestones: Vec<Milestone>,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PauseUpdatedData {
+    pub paused: bool,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct OwnerUpdatedData {
+    pub old_owner_id: AccountId,
+    pub new_owner_id: AccountId,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+struct EventLog<'a> {
+    standard: &'static str,
+    version: &'static str,
+    #[serde(flatten)]
+    event: &'a Event,
+}
+
+impl Event {
+    pub fn to_json_string(&self) -> String {
+        serde_json::to_string(&EventLog {
+            standard: EVENT_STANDARD,
+            version: EVENT_STANDARD_VERSION,
+            event: self,
+        })
+        .unwrap()
+    }
+
+    /// Writes the event to the logs with the `EVENT_JSON:` prefix
+    pub fn emit(&self) {
+        env::log_str(&format!("EVENT_JSON:{}", self.to_json_string()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn account(account_id: &str) -> AccountId {
+        account_id.parse().unwrap()
+    }
+
+    #[test]
+    fn check_task_created_event() {
+        let event = Event::TaskCreated(vec![TaskCreatedData {
+            account_id: account("lrn.testnet"),
+            record_id: 1,
+            deadline_time: U64(1_658_179_621_000_000_000),
+            deposit: U128(3_000_000_000_000_000_000_000_000),
+            token_id: None,
+        }]);
+
+        assert_eq!(
+            event.to_json_string(),
+            r#"{"standard":"dear_procrastination","version":"1.0.0","event":"task_created","data":[{"account_id":"lrn.testnet","record_id":1,"deadline_time":"1658179621000000000","deposit":"3000000000000000000000000"}]}"#
+        );
+    }
+
+    #[test]
+    fn check_task_completed_event() {
+        let event = Event::TaskCompleted(vec![TaskCompletedData {
+            account_id: account("lrn.testnet"),
+            record_id: 2,
+            on_time: false,
+        }]);
+
+        assert_eq!(
+            event.to_json_string(),
+            r#"{"standard":"dear_procrastination","version":"1.0.0","event":"task_completed","data":[{"account_id":"lrn.testnet","record_id":2,"on_time":false}]}"#
+        );
+    }
+
+    #[test]
+    fn check_deposit_events() {
+        let deposit = || {
+            vec![DepositData {
+                account_id: account("lrn.testnet"),
+                record_id: 1,
+                amount: U128(5),
+                token_id: Some(account("usdc.testnet")),
+            }]
+        };
+
+        assert_eq!(
+            Event::DepositRefunded(deposit()).to_json_string(),
+            r#"{"standard":"dear_procrastination","version":"1.0.0","event":"deposit_refunded","data":[{"account_id":"lrn.testnet","record_id":1,"amount":"5","token_id":"usdc.testnet"}]}"#
+        );
+        assert_eq!(
+            Event::DepositWithheld(deposit()).to_json_string(),
+            r#"{"standard":"dear_procrastination","version":"1.0.0","event":"deposit_withheld","data":[{"account_id":"lrn.testnet","record_id":1,"amount":"5","token_id":"usdc.testnet"}]}"#
+        );
+    }
+
+    #[test]
+    fn check_task_settled_event() {
+        let event = Event::TaskSettled(vec![
+            TaskSettledData {
+                account_id: account("lrn.testnet"),
+                record_id: 1,
+                keeper_id: account("keeper.testnet"),
+                keeper_fee: U128(30),
+            },
+            TaskSettledData {
+                account_id: account("lrn.testnet"),
+                record_id: 3,
+                keeper_id: account("keeper.testnet"),
+                keeper_fee: U128(40),
+            },
+        ]);
+
+        assert_eq!(
+            event.to_json_string(),
+            r#"{"standard":"dear_procrastination","version":"1.0.0","event":"task_settled","data":[{"account_id":"lrn.testnet","record_id":1,"keeper_id":"keeper.testnet","keeper_fee":"30"},{"account_id":"lrn.testnet","record_id":3,"keeper_id":"keeper.testnet","keeper_fee":"40"}]}"#
+        );
+    }
+
+    #[test]
+    fn check_admin_events() {
+        assert_eq!(
+            Event::PauseUpdated(vec![PauseUpdatedData { paused: true }]).to_json_string(),
+            r#"{"standard":"dear_procrastination","version":"1.0.0","event":"pause_updated","data":[{"paused":true}]}"#
+        );
+        assert_eq!(
+            Event::OwnerUpdated(vec![OwnerUpdatedData {
+                old_owner_id: account("owner.testnet"),
+                new_owner_id: account("lrn.testnet"),
+            }])
+            .to_json_string(),
+            r#"{"standard":"dear_procrastination","version":"1.0.0","event":"owner_updated","data":[{"old_owner_id":"owner.testnet","new_owner_id":"lrn.testnet"}]}"#
+        );
+    }
+
+    #[test]
+    fn check_challenge_settled_event() {
+        let event = Event::ChallengeSettled(vec![ChallengeSettledData {
+            challenge_id: 1,
+            winners: 2,
+            failed: 1,
+            payout: U128(14),
+            platform_fee: U128(2),
+        }]);
+
+        assert_eq!(
+            event.to_json_string(),
+            r#"{"standard":"dear_procrastination","version":"1.0.0","event":"challenge_settled","data":[{"challenge_id":1,"winners":2,"failed":1,"payout":"14","platform_fee":"2"}]}"#
+        );
+    }
+}
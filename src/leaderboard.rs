@@ -0,0 +1,108 @@
+use crate::*;
+
+/// Maximal number of users on every leaderboard
+pub(crate) const MAX_LEADERBOARD_SIZE: usize = 100;
+const DEFAULT_LEADERBOARD_LIMIT: u32 = 10;
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum LeaderboardMetric {
+    /// Share of tasks completed on time among settled tasks, in basis points.
+    /// Only users with at least `leaderboard_min_tasks` settled tasks are listed
+    OnTimeRate,
+    /// Deposits paid for creation of tasks, in yoctoNear
+    TotalStaked,
+    /// Deposits returned for tasks completed on time, in yoctoNear
+    TotalRecovered,
+}
+
+impl LeaderboardMetric {
+    pub const ALL: [LeaderboardMetric; 3] = [
+        LeaderboardMetric::OnTimeRate,
+        LeaderboardMetric::TotalStaked,
+        LeaderboardMetric::TotalRecovered,
+    ];
+
+    /// Returns the score of the user, `None` if the user can not be listed yet
+    fn score(&self, stats: &UserStats, min_tasks: u32) -> Option<Balance> {
+        match self {
+            LeaderboardMetric::OnTimeRate => {
+                let settled = stats.completed_on_time + stats.withheld;
+                if settled == 0 || settled < min_tasks as u64 {
+                    return None;
+                }
+                Some((stats.completed_on_time * crate::config::MAX_BPS as u64 / settled) as Balance)
+            }
+            LeaderboardMetric::TotalStaked => Some(stats.total_staked.0),
+            LeaderboardMetric::TotalRecovered => Some(stats.total_refunded.0),
+        }
+        .filter(|score| *score > 0)
+    }
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct LeaderboardEntry {
+    pub account_id: AccountId,
+    pub score: U128,
+}
+
+impl Contract {
+    /// Moves the user to the place matching the current stats on every leaderboard,
+    /// users that are not listed publicly are removed
+    pub(crate) fn update_leaderboards(&mut self, account_id: &AccountId, user_records: &UserRecords) {
+        for metric in LeaderboardMetric::ALL.iter() {
+            let mut leaderboard = self.leaderboards.get(metric).unwrap_or_default();
+            let listed = leaderboard
+                .iter()
+                .position(|entry| &entry.account_id == account_id);
+            if let Some(index) = listed {
+                leaderboard.remove(index);
+            }
+
+            let score = match user_records.public_stats {
+                true => metric.score(&user_records.stats, self.config.leaderboard_min_tasks),
+                false => None,
+            };
+            if let Some(score) = score {
+                // Users with the same score keep the order in which they reached it
+                let index = leaderboard
+                    .iter()
+                    .position(|entry| entry.score.0 < score)
+                    .unwrap_or(leaderboard.len());
+                leaderboard.insert(
+                    index,
+                    LeaderboardEntry {
+                        account_id: account_id.clone(),
+                        score: U128(score),
+                    },
+                );
+                leaderboard.truncate(MAX_LEADERBOARD_SIZE);
+            } else if listed.is_none() {
+                continue;
+            }
+            self.leaderboards.insert(metric, &leaderboard);
+        }
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Returns the best users by the metric, only users that opted in are listed
+    pub fn get_leaderboard(&self, metric: LeaderboardMetric, limit: Option<u32>) -> Vec<LeaderboardEntry> {
+        let mut leaderboard = self.leaderboards.get(&metric).unwrap_or_default();
+        leaderboard.truncate(limit.unwrap_or(DEFAULT_LEADERBOARD_LIMIT) as usize);
+        leaderboard
+    }
+
+    /// Lists the caller on the leaderboards or removes them from there
+    pub fn set_public_stats(&mut self, public: bool) {
+        let account_id = env::predecessor_account_id();
+        self.assert_migrated(&account_id);
+        let mut user_records = self
+            .get_user_records(&account_id)
+            .unwrap_or_else(|| UserRecords::new(&account_id));
+        user_records.public_stats = public;
+        self.set_user_records(&account_id, user_records);
+    }
+}
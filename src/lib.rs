@@ -1,35 +1,117 @@
+mod achievement;
+mod api;
+mod beneficiary;
+mod calendar;
+mod challenge;
+mod config;
+mod deadline;
+mod editing;
+mod events;
+mod leaderboard;
+mod proof;
+mod recurring;
+mod refund;
+mod settlement;
+mod stats;
+mod template;
+mod token;
+mod treasury;
+mod upgrade;
 mod utils;
+mod verification;
+mod views;
 mod web4;
 
-use crate::utils::unordered_map_pagination;
+pub use crate::achievement::{Milestone, MilestoneKind};
+pub use crate::api::{ApiError, ApiProof, ApiStats, ApiTask, ApiTaskPage};
+pub use crate::challenge::{Challenge, Participant};
+pub use crate::config::Config;
+pub use crate::deadline::{Deadline, DeadlineFormat};
+pub use crate::editing::{ExtensionPolicy, TaskChange, TaskEdit};
+pub use crate::leaderboard::{LeaderboardEntry, LeaderboardMetric};
+use crate::events::{DepositData, Event, TaskCompletedData, TaskCreatedData};
+pub use crate::proof::Proof;
+pub use crate::recurring::{RecurringTask, Schedule};
+pub use crate::stats::UserStats;
+pub use crate::treasury::{Treasury, TreasuryStats};
+pub use crate::verification::{ClaimStatus, CompletionClaim, VerificationOutcome};
+pub use crate::views::TaskFilter;
+use crate::achievement::default_milestones;
+use crate::beneficiary::Donation;
+use crate::upgrade::{RecordV1, UserRecordsV1, UserRecordsV2, STATE_VERSION, STATE_VERSION_KEY};
 use core::option::Option;
-use std::borrow::Borrow;
 use near_sdk::Balance;
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
-use near_sdk::collections::{LookupMap, UnorderedMap};
+use near_contract_standards::non_fungible_token::{NonFungibleToken, TokenId};
+use near_sdk::collections::{LookupMap, LookupSet, UnorderedMap};
+use std::collections::HashMap;
 use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::json_types::{U128, U64};
 use near_sdk::Promise;
-use near_sdk::{env, near_bindgen, AccountId, BorshStorageKey, Timestamp};
-use num_traits::cast::ToPrimitive;
-
-const MIN_DEPOSIT: u128 = 3000000000000000000000000;
+use near_sdk::{env, near_bindgen, AccountId, BorshStorageKey, CryptoHash, PanicOnDefault, Timestamp};
 
 #[near_bindgen]
-#[derive(BorshSerialize, BorshDeserialize)]
+#[derive(BorshSerialize, BorshDeserialize, PanicOnDefault)]
 pub struct Contract {
+    /// Account that is allowed to upgrade and configure the contract
+    pub owner_id: AccountId,
+    /// Settings of the service
+    pub config: Config,
+    /// When true, creation of new tasks is stopped
+    pub paused: bool,
+    /// Ledger of deposits paid, refunded and withheld by the service
+    pub treasury: Treasury,
     /// A list of users
-    pub common_records: LookupMap<AccountId, UserRecords>,
+    pub common_records: LookupMap<AccountId, VersionedUserRecords>,
+    /// Users whose records are still stored in the layout of the first release
+    pub legacy_records: LookupMap<AccountId, UserRecordsV1>,
+    /// Ledgers of deposits paid in fungible tokens
+    pub token_treasuries: LookupMap<AccountId, Treasury>,
+    /// Best users by every metric, sorted by score
+    pub leaderboards: LookupMap<LeaderboardMetric, Vec<LeaderboardEntry>>,
+    /// Tasks done by groups of users
+    pub challenges: UnorderedMap<i64, Challenge>,
+    /// Uniq id of challenge, increases by increment
+    pub challenge_id: i64,
+    /// Achievement tokens minted to users for reaching milestones
+    pub nft: NonFungibleToken,
+    /// Milestones achievements are minted for
+    pub milestones: Vec<Milestone>,
+    /// Achievement tokens that can not be transferred
+    pub soulbound_tokens: LookupSet<TokenId>,
 }
 
 #[derive(BorshDeserialize, BorshSerialize)]
 pub struct UserRecords {
     /// A list of task records
-    pub user_records: UnorderedMap<i64, Record>,
+    pub user_records: UnorderedMap<i64, VersionedRecord>,
     /// Uniq id of record, increases by increment
     pub record_id: i64,
+    /// Refunded deposits that were not claimed by the user yet, in yoctoNear
+    pub claimable_refunds: Balance,
+    /// Refunded deposits paid in fungible tokens that were not claimed by the user yet
+    pub claimable_tokens: HashMap<AccountId, Balance>,
+    /// Habits of the user, their occurrences are stored in `user_records`
+    pub recurring_tasks: UnorderedMap<i64, RecurringTask>,
+    /// Uniq id of recurring task, increases by increment
+    pub recurring_id: i64,
+    /// Counters of the user's tasks
+    pub stats: UserStats,
+    /// When true, the user is listed on the leaderboards
+    pub public_stats: bool,
+    /// Ids of milestones the user got achievement tokens for
+    pub achievements: Vec<String>,
+}
+
+/// Every layout of `UserRecords` that may be found in the storage
+#[allow(clippy::large_enum_variant)]
+#[derive(BorshDeserialize, BorshSerialize)]
+pub enum VersionedUserRecords {
+    V2(UserRecordsV2),
+    Current(UserRecords),
 }
 
-#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize)]
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
 #[serde(crate = "near_sdk::serde")]
 pub struct Record {
     /// Description of task
@@ -44,254 +126,542 @@ pub struct Record {
     pub deadline_time: Timestamp,
     /// User balance at the time of task creation, in Near
     pub account_balance: Balance,
-    /// User deposit status, can be "Contributed", "Refunded", "Withheld"
+    /// User deposit status, can be "Contributed", "Refunded", "Withheld", "Cancelled".
+    /// A refunded deposit is added to the claimable refunds of the user
     pub deposit_status: DepositStatus,
+    /// Time of creation of the task, in nanoseconds
+    pub created_at: Timestamp,
+    /// Changes made to the task after its creation
+    pub history: Vec<TaskEdit>,
+    /// Fungible token the deposit was paid in, `None` for NEAR
+    pub token_id: Option<AccountId>,
+    /// Accountability partner who has to confirm completion of the task
+    pub verifier: Option<AccountId>,
+    /// Account that receives the deposit if the task fails
+    pub beneficiary: Option<AccountId>,
+    /// The last completion claim submitted to the verifier
+    pub completion_claim: Option<CompletionClaim>,
+    /// Evidence of completion submitted before the deadline
+    pub proofs: Vec<Proof>,
+    /// Recurring task the record is an occurrence of
+    pub recurring_id: Option<i64>,
+}
+
+/// Task as it is passed to the contract by `create_task` or in the message of `ft_transfer_call`
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct NewTask {
+    pub task: String,
+    pub deadline_time: Deadline,
+    /// Accountability partner who has to confirm completion of the task
+    pub verifier: Option<AccountId>,
+    /// Account that receives the deposit if the task fails
+    pub beneficiary: Option<AccountId>,
+}
+
+/// Every layout of `Record` that may be found in the storage,
+/// old layouts are converted to the current one when read
+#[derive(BorshDeserialize, BorshSerialize)]
+pub enum VersionedRecord {
+    V1(RecordV1),
+    /// Records written by version 2 of the state kept the layout of the first release
+    V2(RecordV1),
+    Current(Record),
 }
 
 #[derive(BorshSerialize, BorshStorageKey)]
 enum StorageKey {
+    /// Legacy prefix of the users list of the first release
     CommonRecords,
+    /// Legacy prefix that was shared by the task maps of all users
+    #[allow(dead_code)]
     UserRecords,
+    VersionedCommonRecords,
+    VersionedUserRecords { account_hash: CryptoHash },
+    TokenTreasuries,
+    RecurringTasks { account_hash: CryptoHash },
+    Leaderboards,
+    Challenges,
+    NftOwners,
+    NftMetadata,
+    NftEnumeration,
+    SoulboundTokens,
+    LegacyRecordClaims,
 }
 
-#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone)]
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone, PartialEq, Debug)]
 #[serde(crate = "near_sdk::serde")]
 pub enum DepositStatus {
     Contributed,
     Refunded,
     Withheld,
+    /// The task was cancelled by the user, the deposit minus the cancel fee is refunded
+    Cancelled,
 }
 
-impl Default for Contract {
-    fn default() -> Self {
+impl UserRecords {
+    /// Creates an empty task map stored under a prefix unique to the account
+    pub fn new(account_id: &AccountId) -> Self {
+        let account_hash = env::sha256_array(account_id.as_bytes());
         Self {
-            common_records: LookupMap::new(StorageKey::CommonRecords),
+            user_records: UnorderedMap::new(StorageKey::VersionedUserRecords { account_hash }),
+            record_id: 1,
+            claimable_refunds: 0,
+            claimable_tokens: HashMap::new(),
+            recurring_tasks: UnorderedMap::new(StorageKey::RecurringTasks { account_hash }),
+            recurring_id: 1,
+            stats: UserStats::default(),
+            public_stats: false,
+            achievements: Vec::new(),
+        }
+    }
+
+    /// Returns the record converted to the current layout
+    pub fn get_record(&self, record_id: i64) -> Option<Record> {
+        self.user_records.get(&record_id).map(Record::from)
+    }
+
+    /// Iterates over records ordered by id, records are read only when the iterator reaches them
+    pub(crate) fn records(&self) -> impl Iterator<Item = (i64, Record)> + '_ {
+        (1..self.record_id)
+            .filter_map(move |record_id| self.get_record(record_id).map(|record| (record_id, record)))
+    }
+
+    /// Stores the record in the current layout
+    pub fn set_record(&mut self, record_id: i64, record: &Record) {
+        self.user_records
+            .insert(&record_id, &VersionedRecord::Current(record.clone()));
+    }
+
+    /// Adds a refunded deposit paid in NEAR or in the token to the claimable balance
+    pub(crate) fn add_claimable(&mut self, token_id: Option<&AccountId>, amount: Balance) {
+        match token_id {
+            None => self.claimable_refunds += amount,
+            Some(token_id) => *self.claimable_tokens.entry(token_id.clone()).or_insert(0) += amount,
         }
     }
 }
 
-impl Default for UserRecords {
-    fn default() -> Self {
-        Self {
-            user_records: UnorderedMap::new(StorageKey::UserRecords),
-            record_id: 1,
+impl Record {
+    /// Checks whether the deadline has passed while the task is still not completed,
+    /// its deposit is not settled and no completion claim or proof locked it in time
+    pub fn is_overdue(&self) -> bool {
+        !self.is_complete_status
+            && self.deposit_status == DepositStatus::Contributed
+            && !self.has_pending_claim()
+            && !self.is_on_time()
+    }
+}
+
+impl VersionedUserRecords {
+    /// Converts records of the account to the current layout
+    pub(crate) fn into_current(self, account_id: &AccountId) -> UserRecords {
+        match self {
+            VersionedUserRecords::V2(user_records) => user_records.into_current(account_id),
+            VersionedUserRecords::Current(user_records) => user_records,
+        }
+    }
+}
+
+impl From<VersionedRecord> for Record {
+    fn from(record: VersionedRecord) -> Self {
+        match record {
+            VersionedRecord::V1(record) | VersionedRecord::V2(record) => record.into(),
+            VersionedRecord::Current(record) => record,
         }
     }
 }
 
-#[near_bindgen]
 impl Contract {
-    /// The method creates a task
-    /// to create a task it is necessary to make a deposit of at least 3 Near
-    /// it is also necessary to specify the deadline for the task in Timestamp
-    #[payable]
-    pub fn create_task(
+    /// Returns records of the user in the current layout,
+    /// users of the first release that were not migrated yet are not visible
+    pub(crate) fn get_user_records(&self, account_id: &AccountId) -> Option<UserRecords> {
+        self.common_records
+            .get(account_id)
+            .map(|user_records| user_records.into_current(account_id))
+    }
+
+    pub(crate) fn set_user_records(&mut self, account_id: &AccountId, mut user_records: UserRecords) {
+        self.update_leaderboards(account_id, &user_records);
+        self.award_achievements(account_id, &mut user_records);
+        self.common_records
+            .insert(account_id, &VersionedUserRecords::Current(user_records));
+    }
+
+    /// Changes the ledger of deposits paid in the token, `None` stands for NEAR
+    pub(crate) fn update_treasury<F>(&mut self, token_id: Option<&AccountId>, update: F)
+    where
+        F: FnOnce(&mut Treasury),
+    {
+        match token_id {
+            None => update(&mut self.treasury),
+            Some(token_id) => {
+                let mut treasury = self.token_treasuries.get(token_id).unwrap_or_default();
+                update(&mut treasury);
+                self.token_treasuries.insert(token_id, &treasury);
+            }
+        }
+    }
+
+    /// Marks the deposit of the record as withheld by the service, returns the donation
+    /// to the beneficiary of the record except the keeper fee and the platform fee
+    pub(crate) fn withhold_deposit(
         &mut self,
-        task: String,
-        deadline_time: Timestamp,
+        account_id: &AccountId,
+        record_id: i64,
+        record: &mut Record,
+        user_records: &mut UserRecords,
+        keeper_fee: Balance,
+    ) -> Option<Donation> {
+        record.deposit_status = DepositStatus::Withheld;
+        let amount = record.guarantee_of_task_completion;
+        user_records.stats.add_withheld(record.token_id.as_ref(), amount);
+        self.update_treasury(record.token_id.as_ref(), |treasury| treasury.total_withheld += amount);
+
+        Event::DepositWithheld(vec![DepositData {
+            account_id: account_id.clone(),
+            record_id,
+            amount: U128(amount),
+            token_id: record.token_id.clone(),
+        }])
+        .emit();
+
+        self.withheld_donation(account_id, record_id, record, keeper_fee)
+    }
+
+    /// Marks the deposit of the record as refunded and adds it to the claimable refunds of the user
+    pub(crate) fn refund_deposit(
+        &mut self,
+        account_id: &AccountId,
+        record_id: i64,
+        record: &mut Record,
+        user_records: &mut UserRecords,
     ) {
+        record.account_balance = env::account_balance();
+        record.deposit_status = DepositStatus::Refunded;
+        let amount = record.guarantee_of_task_completion;
+        user_records.add_claimable(record.token_id.as_ref(), amount);
+        user_records.stats.add_completed_on_time(record.token_id.as_ref(), amount);
+        self.update_treasury(record.token_id.as_ref(), |treasury| treasury.total_refunded += amount);
+
+        Event::DepositRefunded(vec![DepositData {
+            account_id: account_id.clone(),
+            record_id,
+            amount: U128(amount),
+            token_id: record.token_id.clone(),
+        }])
+        .emit();
+    }
+
+    /// Creates a task backed by the deposit paid in NEAR or in the token
+    pub(crate) fn internal_create_task(
+        &mut self,
+        account_id: AccountId,
+        new_task: NewTask,
+        deposit: Balance,
+        token_id: Option<AccountId>,
+    ) -> U64 {
+        let NewTask {
+            task,
+            deadline_time,
+            verifier,
+            beneficiary,
+        } = new_task;
+        assert!(!self.paused, "Creation of tasks is paused");
+        if let Some(beneficiary) = &beneficiary {
+            self.assert_valid_beneficiary(&account_id, beneficiary);
+        }
         assert!(
-            env::attached_deposit().borrow().to_u128().unwrap() >= *&MIN_DEPOSIT,
-            "For creation task you need pay minimum 3 Near"
+            verifier.as_ref() != Some(&account_id),
+            "Verifier of the task can not be its owner"
         );
+        assert!(
+            verifier.is_none() || verifier != beneficiary,
+            "Verifier of the task can not be its beneficiary"
+        );
+        assert!(
+            task.len() <= self.config.max_task_length as usize,
+            "Task description is longer than {} bytes",
+            self.config.max_task_length
+        );
+        let deadline_time = self.normalize_deadline(&deadline_time);
 
-        let account_id = env::predecessor_account_id();
+        self.assert_migrated(&account_id);
         let account_balance: Balance = env::account_balance();
 
         let record = Record {
             task,
             is_complete_status: false,
             deadline_time,
-            guarantee_of_task_completion: env::attached_deposit(),
+            guarantee_of_task_completion: deposit,
             account_balance,
             deposit_status: DepositStatus::Contributed,
+            created_at: env::block_timestamp(),
+            history: Vec::new(),
+            token_id,
+            verifier,
+            beneficiary,
+            completion_claim: None,
+            proofs: Vec::new(),
+            recurring_id: None,
         };
 
-        if self.common_records.get(&account_id).is_some() {
-            let mut user_record = self.common_records.get(&account_id).unwrap();
+        let mut user_record = self
+            .get_user_records(&account_id)
+            .unwrap_or_else(|| UserRecords::new(&account_id));
 
-            user_record
-                .user_records
-                .insert(&user_record.record_id, &record);
-            user_record.record_id += 1;
+        let record_id = user_record.record_id;
+        user_record.set_record(record_id, &record);
+        user_record.record_id += 1;
+        user_record.stats.add_created(record.token_id.as_ref(), deposit);
 
-            self.common_records.insert(&account_id, &user_record);
-        }
-        if self.common_records.get(&account_id).is_none() {
-            let mut user_record = UserRecords::default();
+        self.set_user_records(&account_id, user_record);
+        self.update_treasury(record.token_id.as_ref(), |treasury| {
+            treasury.total_contributed += deposit
+        });
 
-            user_record
-                .user_records
-                .insert(&user_record.record_id, &record);
-            user_record.record_id += 1;
+        Event::TaskCreated(vec![TaskCreatedData {
+            account_id,
+            record_id,
+            deadline_time: U64(deadline_time),
+            deposit: U128(deposit),
+            token_id: record.token_id,
+        }])
+        .emit();
 
-            self.common_records.insert(&account_id, &user_record);
-        }
+        U64(deadline_time)
     }
 
-    /// The method allows to get the task by its order number
-    pub fn get_task_by_id(&self, record_id: i64, user_id: AccountId) -> Record {
-        assert!(
-            &self.common_records.get(&user_id).is_some(),
-            "User not found"
+    pub(crate) fn assert_owner(&self) {
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.owner_id,
+            "Only the owner can call this method"
         );
-        return self
-            .common_records
-            .get(&user_id)
-            .unwrap()
-            .user_records
-            .get(&record_id)
-            .unwrap();
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    #[init]
+    pub fn new(owner_id: AccountId, config: Config) -> Self {
+        config.assert_valid();
+        env::storage_write(STATE_VERSION_KEY, &[STATE_VERSION]);
+        Self {
+            owner_id,
+            config,
+            paused: false,
+            treasury: Treasury::default(),
+            common_records: LookupMap::new(StorageKey::VersionedCommonRecords),
+            legacy_records: LookupMap::new(StorageKey::CommonRecords),
+            token_treasuries: LookupMap::new(StorageKey::TokenTreasuries),
+            leaderboards: LookupMap::new(StorageKey::Leaderboards),
+            challenges: UnorderedMap::new(StorageKey::Challenges),
+            challenge_id: 1,
+            nft: NonFungibleToken::new(
+                StorageKey::NftOwners,
+                env::current_account_id(),
+                Some(StorageKey::NftMetadata),
+                Some(StorageKey::NftEnumeration),
+                None::<StorageKey>,
+            ),
+            milestones: default_milestones(),
+            soulbound_tokens: LookupSet::new(StorageKey::SoulboundTokens),
+        }
     }
 
-    /// The method allows to get all user tasks
-    pub fn get_all_user_tasks(&self, user_id: AccountId) -> Vec<(i64, Record)> {
+    /// The method creates a task
+    /// to create a task it is necessary to make a deposit within the limits of the config
+    /// it is also necessary to specify the deadline for the task, it is returned in nanoseconds.
+    /// If the verifier is set, completion of the task has to be confirmed by them,
+    /// if the beneficiary is set, they receive the deposit when the task fails
+    #[payable]
+    pub fn create_task(
+        &mut self,
+        task: String,
+        deadline_time: Deadline,
+        verifier: Option<AccountId>,
+        beneficiary: Option<AccountId>,
+    ) -> U64 {
         assert!(
-            &self.common_records.get(&user_id).is_some(),
-            "User not found"
+            env::attached_deposit() >= self.config.min_deposit.0,
+            "For creation task you need pay minimum {} yoctoNEAR",
+            self.config.min_deposit.0
         );
         assert!(
-            &self
-                .common_records
-                .get(&user_id)
-                .unwrap()
-                .user_records
-                .get(&1)
-                .is_some(),
-            "You have not added tasks yet"
-        );
-        unordered_map_pagination(
-            &self.common_records.get(&user_id).unwrap().user_records,
-            None,
-            None,
-        )
+            env::attached_deposit() <= self.config.max_deposit.0,
+            "For creation task you can pay maximum {} yoctoNEAR",
+            self.config.max_deposit.0
+        );
+        let new_task = NewTask {
+            task,
+            deadline_time,
+            verifier,
+            beneficiary,
+        };
+        self.internal_create_task(env::predecessor_account_id(), new_task, env::attached_deposit(), None)
+    }
+
+    /// The method allows to get the task by its order number
+    pub fn get_task_by_id(&self, record_id: i64, user_id: AccountId) -> Record {
+        self.get_user_records(&user_id)
+            .expect("User not found")
+            .get_record(record_id)
+            .expect("Task not found")
     }
 
     /// The method allows you to complete scheduled tasks
-    /// if the deadline for the task has not expired, the method will return the deposit to the user
+    /// if the deadline for the task has not expired or a proof was submitted before it,
+    /// the deposit is added to the claimable refunds
+    /// of the user, they can be withdrawn with `claim_refunds` or `claim_token_refunds`
     pub fn make_complete_task_status(&mut self, changed_record_id: i64) -> String {
-        if self
-            .common_records
-            .get(&env::predecessor_account_id())
-            .is_some()
-        {
-            let mut changed_user_records = self
-                .common_records
-                .get(&env::predecessor_account_id())
-                .unwrap();
-
-            if changed_user_records
-                .user_records
-                .get(&changed_record_id)
-                .is_some()
-            {
-                let mut record = changed_user_records
-                    .user_records
-                    .get(&changed_record_id)
-                    .unwrap();
-
-                assert_eq!(record.is_complete_status, false, "Task already completed");
-
-                record.is_complete_status = true;
-
-                changed_user_records
-                    .user_records
-                    .insert(&changed_record_id, &record);
-
-                self.common_records
-                    .insert(&env::predecessor_account_id(), &changed_user_records);
-
-                if record.deadline_time > env::block_timestamp() {
-                    Promise::new(env::predecessor_account_id())
-                        .transfer(record.guarantee_of_task_completion);
-                    record.account_balance = env::account_balance();
-                    record.deposit_status = DepositStatus::Refunded;
-                    changed_user_records.user_records.insert(&changed_record_id, &record);
-
-                    return String::from("Deposit refunded ".to_owned() + &*record.guarantee_of_task_completion.to_string());
-                }
-                record.deposit_status = DepositStatus::Withheld;
-                changed_user_records.user_records.insert(&changed_record_id, &record);
-            }
+        let account_id = env::predecessor_account_id();
+        self.assert_migrated(&account_id);
+        let mut changed_user_records = self
+            .get_user_records(&account_id)
+            .expect("User not found");
+        let mut record = changed_user_records
+            .get_record(changed_record_id)
+            .expect("Task not found");
+
+        assert!(!record.is_complete_status, "Task already completed");
+        assert!(
+            !record.has_pending_claim(),
+            "Completion of the task is waiting for the verifier"
+        );
+        assert!(
+            record.verifier.is_none() || !record.is_on_time(),
+            "Completion of the task has to be confirmed by the verifier, use claim_completion"
+        );
+
+        record.is_complete_status = true;
+
+        Event::TaskCompleted(vec![TaskCompletedData {
+            account_id: account_id.clone(),
+            record_id: changed_record_id,
+            on_time: record.is_on_time(),
+        }])
+        .emit();
+
+        if record.deposit_status != DepositStatus::Contributed {
+            changed_user_records.stats.add_completed_late();
+            changed_user_records.set_record(changed_record_id, &record);
+            self.set_user_records(&account_id, changed_user_records);
+            return String::from("Task completed, deposit was already settled");
+        }
 
+        if !record.is_on_time() {
+            changed_user_records.stats.add_completed_late();
+            let donation =
+                self.withhold_deposit(&account_id, changed_record_id, &mut record, &mut changed_user_records, 0);
+            self.send_donations(donation);
+            changed_user_records.set_record(changed_record_id, &record);
+            self.set_user_records(&account_id, changed_user_records);
+            return String::from("Deadline was ended, deposit stayed in service");
         }
-        return String::from("Deadline was ended, deposit stayed in service");
+
+        self.refund_deposit(&account_id, changed_record_id, &mut record, &mut changed_user_records);
+        changed_user_records.set_record(changed_record_id, &record);
+        self.set_user_records(&account_id, changed_user_records);
+
+        format!("Deposit refunded {}", record.guarantee_of_task_completion)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use near_sdk::json_types::ValidAccountId;
-    use near_sdk::test_utils::VMContextBuilder;
-    use near_sdk::{testing_env};
-    use std::convert::TryFrom;
+    use crate::config::DEFAULT_MIN_DEPOSIT;
+    use crate::deadline::NANOS_IN_SECOND;
+    use crate::upgrade::ContractV2;
+    use crate::views::{DEFAULT_TASKS_LIMIT, MAX_COUNTED_TASKS, MAX_TASKS_LIMIT};
+    use crate::beneficiary::MAX_DONATIONS_PER_CALL;
+    use near_sdk::test_utils::{get_created_receipts, get_logs, VMContextBuilder};
+    use near_contract_standards::fungible_token::receiver::FungibleTokenReceiver;
+    use near_contract_standards::non_fungible_token::core::NonFungibleTokenCore;
+    use near_contract_standards::non_fungible_token::enumeration::NonFungibleTokenEnumeration;
+    use near_sdk::{testing_env, PromiseResult, RuntimeFeesConfig, VMConfig};
 
+    const OWNER: &str = "owner.testnet";
+    const NOW: Timestamp = 1_658_000_000_000_000_000;
+    const AFTER_DEADLINE: Timestamp = 1_658_179_622_000_000_000;
     const FIRST_TASK: i64 = 1;
     const COMPLETE_STATUS: bool = true;
 
-    fn to_valid_account(account: &str) -> ValidAccountId {
-        ValidAccountId::try_from(account.to_string()).expect("Invalid account")
+    fn to_valid_account(account: &str) -> AccountId {
+        account.parse().expect("Invalid account")
     }
 
-    fn get_context(predecessor: ValidAccountId) -> VMContextBuilder {
+    fn get_context(predecessor: AccountId) -> VMContextBuilder {
         let mut builder = VMContextBuilder::new();
-        builder.predecessor_account_id(predecessor);
+        builder.predecessor_account_id(predecessor).block_timestamp(NOW);
         builder
     }
 
+    fn deadline(seconds: u64) -> Deadline {
+        Deadline::Format(DeadlineFormat::Seconds(seconds))
+    }
+
+    const TOKEN: &str = "usdc.testnet";
+    const TOKEN_MIN_DEPOSIT: u128 = 5_000_000;
+
+    fn token_config() -> Config {
+        let mut config = Config::default();
+        config
+            .token_min_deposits
+            .insert(to_valid_account(TOKEN), U128(TOKEN_MIN_DEPOSIT));
+        config
+    }
+
+    fn token_msg(seconds: u64) -> String {
+        format!(r#"{{"task": "default task", "deadline_time": {{"seconds": {}}}}}"#, seconds)
+    }
+
     fn create_record(
         &deposit: &u128,
         &status: &bool,
         &deadline: &Timestamp,
     ) -> Record {
-        let record = Record {
+        Record {
             task: "default task".to_string(),
             is_complete_status: status,
             deadline_time: deadline,
             guarantee_of_task_completion: deposit,
             account_balance: env::account_balance(),
             deposit_status: DepositStatus::Contributed,
-        };
-        return record;
+            created_at: env::block_timestamp(),
+            history: Vec::new(),
+            token_id: None,
+            verifier: None,
+            beneficiary: None,
+            completion_claim: None,
+            proofs: Vec::new(),
+            recurring_id: None,
+        }
     }
 
     #[test]
     fn check_creation_of_task() {
-        let context = get_context(to_valid_account("lrn.testnet"));
-        testing_env!(context.build());
+        let mut context = get_context(to_valid_account("lrn.testnet"));
+        testing_env!(context.attached_deposit(DEFAULT_MIN_DEPOSIT).build());
         let account = context.build().predecessor_account_id;
-        let mut received_contract = Contract {
-            common_records: LookupMap::new(StorageKey::CommonRecords),
-        };
+        let mut received_contract = Contract::new(to_valid_account(OWNER), Config::default());
 
-        received_contract.create_task("default task".to_string(),  1658179621);
+        received_contract.create_task("default task".to_string(), deadline(1658179621), None, None);
 
-        let first_record = create_record(&3000000000000000000000000, &false, &1658179621);
+        let first_record = create_record(&DEFAULT_MIN_DEPOSIT, &false, &1_658_179_621_000_000_000);
 
         let received_task = received_contract
-            .common_records
-            .get(&account)
-            .unwrap()
-            .user_records.get(&1).unwrap().task;
+            .get_user_records(&account).unwrap().get_record(1).unwrap().task;
 
         let received_deposit = received_contract
-            .common_records
-            .get(&account)
-            .unwrap()
-            .user_records.get(&1).unwrap().guarantee_of_task_completion;
+            .get_user_records(&account).unwrap().get_record(1).unwrap().guarantee_of_task_completion;
 
         let received_status = received_contract
-            .common_records
-            .get(&account)
-            .unwrap()
-            .user_records.get(&1).unwrap().is_complete_status;
+            .get_user_records(&account).unwrap().get_record(1).unwrap().is_complete_status;
 
         let received_deadline_time = received_contract
-            .common_records
-            .get(&account)
-            .unwrap()
-            .user_records.get(&1).unwrap().deadline_time;
+            .get_user_records(&account).unwrap().get_record(1).unwrap().deadline_time;
 
         assert_eq!(first_record.task, received_task);
         assert_eq!(first_record.guarantee_of_task_completion, received_deposit);
@@ -304,70 +674,1891 @@ mod tests {
     fn check_min_deposit_for_creation_task() {
         let context = get_context(to_valid_account("lrn.testnet"));
         testing_env!(context.build());
-        let mut received_contract = Contract {
-            common_records: LookupMap::new(StorageKey::CommonRecords),
-        };
+        let mut received_contract = Contract::new(to_valid_account(OWNER), Config::default());
 
-        received_contract.create_task("default task".to_string(), 1658179621);
+        received_contract.create_task("default task".to_string(), deadline(1658179621), None, None);
     }
 
     #[test]
     fn check_getting_of_all_records() {
-        let context = get_context(to_valid_account("lrn.testnet"));
-        testing_env!(context.build());
+        let mut context = get_context(to_valid_account("lrn.testnet"));
+        testing_env!(context.attached_deposit(DEFAULT_MIN_DEPOSIT).build());
         let account = context.build().predecessor_account_id;
-        let mut received_contract = Contract {
-            common_records: LookupMap::new(StorageKey::CommonRecords),
-        };
+        let mut received_contract = Contract::new(to_valid_account(OWNER), Config::default());
 
-        received_contract.create_task("default task".to_string(), 1658179621);
-        received_contract.create_task("default task".to_string(), 1658179622);
+        received_contract.create_task("default task".to_string(), deadline(1658179621), None, None);
+        received_contract.create_task("default task".to_string(), deadline(1658179622), None, None);
 
-        let vec = received_contract.get_all_user_tasks(account);
+        let vec = received_contract.get_user_tasks(account.clone(), None, None, None);
 
         assert_eq!(vec.len(), 2);
+        assert_eq!(received_contract.get_user_task_count(account, None), 2);
     }
 
     #[test]
-    #[should_panic]
     fn check_getting_all_task_without_created_tasks() {
         let context = get_context(to_valid_account("lrn.testnet"));
         testing_env!(context.build());
         let account = context.build().predecessor_account_id;
-        let received_contract = Contract {
-            common_records: LookupMap::new(StorageKey::CommonRecords),
+        let received_contract = Contract::new(to_valid_account(OWNER), Config::default());
+
+        assert!(received_contract.get_user_tasks(account.clone(), None, None, None).is_empty());
+        assert_eq!(received_contract.get_user_task_count(account, None), 0);
+    }
+
+    #[test]
+    fn check_pagination_of_user_tasks() {
+        let mut context = get_context(to_valid_account("lrn.testnet"));
+        testing_env!(context.attached_deposit(DEFAULT_MIN_DEPOSIT).build());
+        let account = context.build().predecessor_account_id;
+        let mut received_contract = Contract::new(to_valid_account(OWNER), Config::default());
+
+        for seconds in 1658179621..1658179626 {
+            received_contract.create_task("default task".to_string(), deadline(seconds), None, None);
+        }
+
+        let page = received_contract.get_user_tasks(account.clone(), Some(1), Some(2), None);
+        let record_ids: Vec<i64> = page.iter().map(|(record_id, _)| *record_id).collect();
+        assert_eq!(record_ids, vec![2, 3]);
+        assert_eq!(received_contract.get_user_task_count(account, Some(TaskFilter::default())), 5);
+    }
+
+    #[test]
+    fn check_limits_of_user_tasks() {
+        let mut context = get_context(to_valid_account("lrn.testnet"));
+        testing_env!(context.attached_deposit(DEFAULT_MIN_DEPOSIT).build());
+        let account = context.build().predecessor_account_id;
+        let mut received_contract = Contract::new(to_valid_account(OWNER), Config::default());
+
+        for _ in 0..=MAX_COUNTED_TASKS {
+            testing_env!(context.build());
+            received_contract.create_task("default task".to_string(), deadline(1659179621), None, None);
+        }
+
+        let tasks = received_contract.get_user_tasks(account.clone(), None, None, None);
+        assert_eq!(tasks.len() as u64, DEFAULT_TASKS_LIMIT);
+        let tasks = received_contract.get_user_tasks(account.clone(), None, Some(u64::MAX), None);
+        assert_eq!(tasks.len() as u64, MAX_TASKS_LIMIT);
+
+        let open = TaskFilter { is_complete: Some(false), ..TaskFilter::default() };
+        assert_eq!(received_contract.get_user_task_count(account.clone(), Some(open)), MAX_COUNTED_TASKS as u64);
+        assert_eq!(received_contract.get_user_task_count(account, None), MAX_COUNTED_TASKS as u64 + 1);
+    }
+
+    #[test]
+    fn check_filtering_of_user_tasks() {
+        let mut context = get_context(to_valid_account("lrn.testnet"));
+        testing_env!(context.attached_deposit(DEFAULT_MIN_DEPOSIT).build());
+        let account = context.build().predecessor_account_id;
+        let mut received_contract = Contract::new(to_valid_account(OWNER), Config::default());
+
+        received_contract.create_task("first task".to_string(), deadline(1658179621), None, None);
+        received_contract.create_task("second task".to_string(), deadline(1658179700), None, None);
+        received_contract.create_task("third task".to_string(), deadline(1658179800), None, None);
+        received_contract.make_complete_task_status(FIRST_TASK);
+
+        let completed = TaskFilter {
+            is_complete: Some(true),
+            ..Default::default()
+        };
+        let tasks = received_contract.get_user_tasks(account.clone(), None, None, Some(completed));
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].0, FIRST_TASK);
+
+        let refunded = TaskFilter {
+            deposit_status: Some(DepositStatus::Refunded),
+            ..Default::default()
+        };
+        assert_eq!(received_contract.get_user_task_count(account.clone(), Some(refunded)), 1);
+
+        let deadline_range = TaskFilter {
+            deadline_from: Some(U64(1658179700 * NANOS_IN_SECOND)),
+            deadline_to: Some(U64(1658179800 * NANOS_IN_SECOND)),
+            ..Default::default()
+        };
+        assert_eq!(received_contract.get_user_task_count(account.clone(), Some(deadline_range)), 2);
+
+        testing_env!(context.block_timestamp(1658179750 * NANOS_IN_SECOND).build());
+        let overdue = TaskFilter {
+            overdue: Some(true),
+            ..Default::default()
         };
-        received_contract.get_all_user_tasks(account);
+        let tasks = received_contract.get_user_tasks(account, None, None, Some(overdue));
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].1.task, "second task");
     }
 
     #[test]
     fn check_changing_status_of_task() {
         let mut context = get_context(to_valid_account("lrn.testnet"));
-        testing_env!(context.build());
+        testing_env!(context.attached_deposit(DEFAULT_MIN_DEPOSIT).build());
         let account = context.build().predecessor_account_id;
-        let mut received_contract = Contract {
-            common_records: LookupMap::new(StorageKey::CommonRecords),
-        };
-        let attached_dep: Balance = 3000000000000000000000000;
-        context.build().attached_deposit = attached_dep;
+        let mut received_contract = Contract::new(to_valid_account(OWNER), Config::default());
 
-        received_contract.create_task("default task".to_string(), 1658179621);
+        received_contract.create_task("default task".to_string(), deadline(1658179621), None, None);
         received_contract.make_complete_task_status(FIRST_TASK);
-        let received_status = received_contract.common_records.get(&account).unwrap().user_records.get(&FIRST_TASK).unwrap().is_complete_status;
+        let received_status = received_contract.get_user_records(&account).unwrap().get_record(FIRST_TASK).unwrap().is_complete_status;
         assert_eq!(received_status, COMPLETE_STATUS);
     }
 
     #[test]
     #[should_panic]
     fn check_panic_when_trying_complete_completed_task() {
-        let context = get_context(to_valid_account("lrn.testnet"));
+        let mut context = get_context(to_valid_account("lrn.testnet"));
+        testing_env!(context.attached_deposit(DEFAULT_MIN_DEPOSIT).build());
+        let mut received_contract = Contract::new(to_valid_account(OWNER), Config::default());
+
+        received_contract.create_task("default task".to_string(), deadline(1658179621), None, None);
+        received_contract.make_complete_task_status(FIRST_TASK);
+        received_contract.make_complete_task_status(FIRST_TASK);
+    }
+
+    #[test]
+    fn check_records_of_different_users_are_separated() {
+        let mut context = get_context(to_valid_account("alice.testnet"));
+        testing_env!(context.attached_deposit(DEFAULT_MIN_DEPOSIT).build());
+        let mut received_contract = Contract::new(to_valid_account(OWNER), Config::default());
+
+        received_contract.create_task("alice task".to_string(), deadline(1658179621), None, None);
+
+        testing_env!(context
+            .predecessor_account_id(to_valid_account("bob.testnet"))
+            .build());
+        received_contract.create_task("bob task".to_string(), deadline(1658179621), None, None);
+        received_contract.create_task("bob second task".to_string(), deadline(1658179622), None, None);
+
+        let alice_tasks =
+            received_contract.get_user_tasks(to_valid_account("alice.testnet"), None, None, None);
+        let bob_tasks =
+            received_contract.get_user_tasks(to_valid_account("bob.testnet"), None, None, None);
+
+        assert_eq!(alice_tasks.len(), 1);
+        assert_eq!(alice_tasks[0].1.task, "alice task");
+        assert_eq!(bob_tasks.len(), 2);
+        assert_eq!(bob_tasks[0].1.task, "bob task");
+    }
+
+    #[test]
+    fn check_migration_of_legacy_state() {
+        let mut context = get_context(to_valid_account("dear_procrastination.testnet"));
+        context.current_account_id(to_valid_account("dear_procrastination.testnet"));
         testing_env!(context.build());
-        let mut received_contract = Contract {
-            common_records: LookupMap::new(StorageKey::CommonRecords),
+        let account = to_valid_account("lrn.testnet");
+
+        let mut legacy_records = UserRecordsV1 {
+            user_records: UnorderedMap::new(StorageKey::UserRecords),
+            record_id: 1,
         };
+        legacy_records.user_records.insert(
+            &1,
+            &RecordV1 {
+                task: "default task".to_string(),
+                is_complete_status: false,
+                guarantee_of_task_completion: DEFAULT_MIN_DEPOSIT,
+                deadline_time: 1658179621,
+                account_balance: env::account_balance(),
+                deposit_status: DepositStatus::Contributed,
+            },
+        );
+        legacy_records.record_id = 2;
+        let mut legacy_users = LookupMap::new(StorageKey::CommonRecords);
+        legacy_users.insert(&account, &legacy_records);
+        env::state_write(&legacy_users);
 
-        received_contract.create_task("default task".to_string(), 1658179621);
-        received_contract.make_complete_task_status(FIRST_TASK);
+        let mut received_contract = Contract::migrate();
+        assert_eq!(received_contract.owner_id, to_valid_account("dear_procrastination.testnet"));
+        assert!(received_contract.get_user_records(&account).is_none());
+
+        assert_eq!(received_contract.migrate_user_records(vec![account.clone()]), 1);
+        assert_eq!(received_contract.migrate_user_records(vec![account.clone()]), 0);
+
+        let user_records = received_contract.get_user_records(&account).unwrap();
+        assert_eq!(user_records.record_id, 2);
+        assert_eq!(user_records.get_record(1).unwrap().deadline_time, 1_658_179_621_000_000_000);
+        assert_eq!(
+            user_records.get_record(1).unwrap().guarantee_of_task_completion,
+            DEFAULT_MIN_DEPOSIT
+        );
+        assert_eq!(
+            received_contract.get_treasury_stats().outstanding_deposits.0,
+            DEFAULT_MIN_DEPOSIT
+        );
+    }
+
+    /// Writes the state of the first release where alice has two tasks in the map shared
+    /// by everyone and carol, who numbered her tasks from 1 as well, has the first id of alice
+    fn write_legacy_state_with_shared_ids() {
+        let legacy_record = |task: &str, deposit: Balance| RecordV1 {
+            task: task.to_string(),
+            is_complete_status: false,
+            guarantee_of_task_completion: deposit,
+            deadline_time: 1658179621,
+            account_balance: env::account_balance(),
+            deposit_status: DepositStatus::Contributed,
+        };
+        let mut alice_records = UserRecordsV1 {
+            user_records: UnorderedMap::new(StorageKey::UserRecords),
+            record_id: 3,
+        };
+        alice_records.user_records.insert(&1, &legacy_record("alice task", DEFAULT_MIN_DEPOSIT));
+        alice_records.user_records.insert(&2, &legacy_record("second alice task", 2 * DEFAULT_MIN_DEPOSIT));
+        // The map of carol has the length of her own writes only
+        let carol_records = UserRecordsV1 {
+            user_records: UnorderedMap::new(StorageKey::UserRecords),
+            record_id: 2,
+        };
+
+        let mut legacy_users = LookupMap::new(StorageKey::CommonRecords);
+        legacy_users.insert(&to_valid_account("alice.testnet"), &alice_records);
+        legacy_users.insert(&to_valid_account("carol.testnet"), &carol_records);
+        env::state_write(&legacy_users);
+    }
+
+    #[test]
+    fn check_migration_of_legacy_users_sharing_task_map() {
+        let mut context = get_context(to_valid_account(OWNER));
+        context.current_account_id(to_valid_account(OWNER));
+        testing_env!(context.build());
+        let alice = to_valid_account("alice.testnet");
+        let carol = to_valid_account("carol.testnet");
+        write_legacy_state_with_shared_ids();
+
+        let mut received_contract = Contract::migrate();
+        assert_eq!(received_contract.migrate_user_records(vec![alice.clone(), carol.clone()]), 2);
+
+        let alice_tasks = received_contract.get_user_tasks(alice, None, None, None);
+        assert_eq!(alice_tasks.len(), 2);
+        assert_eq!(alice_tasks[0].1.task, "alice task");
+        assert_eq!(alice_tasks[1].1.task, "second alice task");
+
+        let carol_records = received_contract.get_user_records(&carol).unwrap();
+        assert_eq!(carol_records.record_id, 2);
+        assert!(carol_records.get_record(1).is_none());
+        assert!(get_logs().iter().any(|log| log.contains(r#""event":"legacy_record_disputed""#)
+            && log.contains(r#""account_id":"carol.testnet","record_id":1,"claimed_by":"alice.testnet""#)));
+
+        // The disputed deposit is counted once
+        assert_eq!(
+            received_contract.get_treasury_stats().outstanding_deposits.0,
+            3 * DEFAULT_MIN_DEPOSIT
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Tasks of the account are waiting for migration by the owner")]
+    fn check_settlement_of_legacy_account_before_migration() {
+        let mut context = get_context(to_valid_account(OWNER));
+        context.current_account_id(to_valid_account(OWNER));
+        testing_env!(context.build());
+        write_legacy_state_with_shared_ids();
+        let mut received_contract = Contract::migrate();
+
+        // Nobody but the owner can decide which account gets a shared id
+        testing_env!(context.predecessor_account_id(to_valid_account("carol.testnet")).build());
+        received_contract.settle_expired_tasks(to_valid_account("carol.testnet"), None, None);
+    }
+
+    fn version_2_record(deposit_status: DepositStatus) -> RecordV1 {
+        RecordV1 {
+            task: "version 2 task".to_string(),
+            is_complete_status: deposit_status == DepositStatus::Refunded,
+            guarantee_of_task_completion: DEFAULT_MIN_DEPOSIT,
+            deadline_time: 1658179621,
+            account_balance: env::account_balance(),
+            deposit_status,
+        }
+    }
+
+    #[test]
+    fn check_record_of_version_2() {
+        // Version 2 wrote records of the first layout as its `Current` variant, the second one
+        let bytes = [vec![1], version_2_record(DepositStatus::Contributed).try_to_vec().unwrap()].concat();
+
+        let record = Record::from(VersionedRecord::try_from_slice(&bytes).unwrap());
+        assert_eq!(record.task, "version 2 task");
+        assert_eq!(record.deadline_time, 1658179621 * NANOS_IN_SECOND);
+        assert_eq!(record.deposit_status, DepositStatus::Contributed);
+        assert!(record.history.is_empty());
+    }
+
+    fn write_version_2_state() {
+        let bob = to_valid_account("bob.testnet");
+        let mut user_records = UserRecordsV2 {
+            user_records: UnorderedMap::new(StorageKey::VersionedUserRecords {
+                account_hash: env::sha256_array(bob.as_bytes()),
+            }),
+            record_id: 3,
+        };
+        user_records
+            .user_records
+            .insert(&1, &VersionedRecord::V2(version_2_record(DepositStatus::Refunded)));
+        user_records
+            .user_records
+            .insert(&2, &VersionedRecord::V2(version_2_record(DepositStatus::Contributed)));
+
+        let mut common_records = LookupMap::new(StorageKey::VersionedCommonRecords);
+        common_records.insert(&bob, &VersionedUserRecords::V2(user_records));
+        env::state_write(&ContractV2 {
+            owner_id: to_valid_account(OWNER),
+            common_records,
+            legacy_records: LookupMap::new(StorageKey::CommonRecords),
+        });
+        env::storage_write(STATE_VERSION_KEY, &[2]);
+    }
+
+    #[test]
+    fn check_migration_of_version_2_state() {
+        let context = get_context(to_valid_account(OWNER));
+        testing_env!(context.build());
+        let bob = to_valid_account("bob.testnet");
+        write_version_2_state();
+
+        let mut received_contract = Contract::migrate();
+        assert_eq!(received_contract.owner_id, to_valid_account(OWNER));
+        assert_eq!(received_contract.get_user_tasks(bob.clone(), None, None, None).len(), 2);
+
+        assert_eq!(received_contract.migrate_user_records(vec![bob.clone()]), 1);
+        assert_eq!(received_contract.migrate_user_records(vec![bob.clone()]), 0);
+        let user_records = received_contract.get_user_records(&bob).unwrap();
+        assert_eq!(user_records.record_id, 3);
+        assert_eq!(user_records.stats.tasks_created, 2);
+        assert_eq!(user_records.stats.completed_on_time, 1);
+        let stats = received_contract.get_treasury_stats();
+        assert_eq!(stats.outstanding_deposits.0, DEFAULT_MIN_DEPOSIT);
+        assert_eq!(stats.total_refunded.0, DEFAULT_MIN_DEPOSIT);
+    }
+
+    #[test]
+    #[should_panic(expected = "Tasks of the account are waiting for migration by the owner")]
+    fn check_settlement_of_version_2_account_before_migration() {
+        let context = get_context(to_valid_account(OWNER));
+        testing_env!(context.build());
+        write_version_2_state();
+        let mut received_contract = Contract::migrate();
+
+        received_contract.settle_expired_tasks(to_valid_account("bob.testnet"), None, None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the owner can call this method")]
+    fn check_upgrade_by_not_owner() {
+        let context = get_context(to_valid_account("lrn.testnet"));
+        testing_env!(context.build());
+        let received_contract = Contract::new(to_valid_account(OWNER), Config::default());
+
+        received_contract.upgrade();
+    }
+
+    #[test]
+    #[should_panic(expected = "Creation of tasks is paused")]
+    fn check_creation_of_task_while_paused() {
+        let mut context = get_context(to_valid_account(OWNER));
+        testing_env!(context.attached_deposit(DEFAULT_MIN_DEPOSIT).build());
+        let mut received_contract = Contract::new(to_valid_account(OWNER), Config::default());
+
+        received_contract.set_paused(true);
+        received_contract.create_task("default task".to_string(), deadline(1658179621), None, None);
+    }
+
+    #[test]
+    fn check_completion_of_task_while_paused() {
+        let mut context = get_context(to_valid_account(OWNER));
+        testing_env!(context.attached_deposit(DEFAULT_MIN_DEPOSIT).build());
+        let account = context.build().predecessor_account_id;
+        let mut received_contract = Contract::new(to_valid_account(OWNER), Config::default());
+
+        received_contract.create_task("default task".to_string(), deadline(1658179621), None, None);
+        received_contract.set_paused(true);
         received_contract.make_complete_task_status(FIRST_TASK);
+
+        let received_status = received_contract.get_user_records(&account).unwrap().get_record(FIRST_TASK).unwrap().is_complete_status;
+        assert_eq!(received_status, COMPLETE_STATUS);
+    }
+
+    #[test]
+    fn check_changing_of_min_deposit() {
+        let mut context = get_context(to_valid_account(OWNER));
+        testing_env!(context.attached_deposit(DEFAULT_MIN_DEPOSIT / 3).build());
+        let mut received_contract = Contract::new(to_valid_account(OWNER), Config::default());
+
+        received_contract.set_config(Config {
+            min_deposit: U128(DEFAULT_MIN_DEPOSIT / 3),
+            ..Config::default()
+        });
+        received_contract.create_task("default task".to_string(), deadline(1658179621), None, None);
+
+        assert_eq!(received_contract.get_config().min_deposit.0, DEFAULT_MIN_DEPOSIT / 3);
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the owner can call this method")]
+    fn check_changing_of_config_by_not_owner() {
+        let context = get_context(to_valid_account("lrn.testnet"));
+        testing_env!(context.build());
+        let mut received_contract = Contract::new(to_valid_account(OWNER), Config::default());
+
+        received_contract.set_paused(true);
+    }
+
+    #[test]
+    fn check_treasury_stats() {
+        let mut context = get_context(to_valid_account("lrn.testnet"));
+        testing_env!(context.attached_deposit(DEFAULT_MIN_DEPOSIT).build());
+        let mut received_contract = Contract::new(to_valid_account(OWNER), Config::default());
+
+        received_contract.create_task("default task".to_string(), deadline(1658179621), None, None);
+        received_contract.create_task("default task".to_string(), deadline(1658179621), None, None);
+        testing_env!(context.block_timestamp(AFTER_DEADLINE).build());
+        received_contract.make_complete_task_status(FIRST_TASK);
+
+        let stats = received_contract.get_treasury_stats();
+        assert_eq!(stats.total_contributed.0, 2 * DEFAULT_MIN_DEPOSIT);
+        assert_eq!(stats.total_refunded.0, 0);
+        assert_eq!(stats.total_withheld.0, DEFAULT_MIN_DEPOSIT);
+        assert_eq!(stats.available_withheld.0, DEFAULT_MIN_DEPOSIT);
+        assert_eq!(stats.outstanding_deposits.0, DEFAULT_MIN_DEPOSIT);
+    }
+
+    #[test]
+    fn check_withdrawal_of_withheld_deposits() {
+        let mut context = get_context(to_valid_account("lrn.testnet"));
+        testing_env!(context.attached_deposit(DEFAULT_MIN_DEPOSIT).build());
+        let mut received_contract = Contract::new(to_valid_account(OWNER), Config::default());
+
+        received_contract.create_task("default task".to_string(), deadline(1658179621), None, None);
+        testing_env!(context.block_timestamp(AFTER_DEADLINE).build());
+        received_contract.make_complete_task_status(FIRST_TASK);
+
+        testing_env!(context
+            .predecessor_account_id(to_valid_account(OWNER))
+            .attached_deposit(0)
+            .build());
+        received_contract.withdraw_withheld(U128(DEFAULT_MIN_DEPOSIT), to_valid_account(OWNER));
+
+        assert_eq!(received_contract.get_treasury_stats().available_withheld.0, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "withheld deposits can be withdrawn")]
+    fn check_withdrawal_of_contributed_deposits() {
+        let mut context = get_context(to_valid_account("lrn.testnet"));
+        testing_env!(context.attached_deposit(DEFAULT_MIN_DEPOSIT).build());
+        let mut received_contract = Contract::new(to_valid_account(OWNER), Config::default());
+
+        received_contract.create_task("default task".to_string(), deadline(1658179621), None, None);
+
+        testing_env!(context
+            .predecessor_account_id(to_valid_account(OWNER))
+            .attached_deposit(0)
+            .build());
+        received_contract.withdraw_withheld(U128(DEFAULT_MIN_DEPOSIT), to_valid_account(OWNER));
+    }
+
+    #[test]
+    fn check_settlement_of_expired_tasks() {
+        let mut context = get_context(to_valid_account("lrn.testnet"));
+        testing_env!(context.attached_deposit(DEFAULT_MIN_DEPOSIT).build());
+        let account = context.build().predecessor_account_id;
+        let mut received_contract = Contract::new(to_valid_account(OWNER), Config::default());
+
+        received_contract.create_task("expired task".to_string(), deadline(1658179621), None, None);
+        received_contract.create_task("open task".to_string(), deadline(1659179621), None, None);
+        received_contract.create_task("completed task".to_string(), deadline(1658179621), None, None);
+        received_contract.make_complete_task_status(3);
+
+        testing_env!(context
+            .predecessor_account_id(to_valid_account("keeper.testnet"))
+            .attached_deposit(0)
+            .block_timestamp(AFTER_DEADLINE)
+            .build());
+        let settled = received_contract.settle_expired_tasks(account.clone(), None, None);
+
+        let user_records = received_contract.get_user_records(&account).unwrap();
+        assert_eq!(settled, 1);
+        assert_eq!(user_records.get_record(1).unwrap().deposit_status, DepositStatus::Withheld);
+        assert_eq!(user_records.get_record(2).unwrap().deposit_status, DepositStatus::Contributed);
+        assert_eq!(user_records.get_record(3).unwrap().deposit_status, DepositStatus::Refunded);
+
+        let keeper_fee = DEFAULT_MIN_DEPOSIT / 100;
+        let stats = received_contract.get_treasury_stats();
+        assert_eq!(stats.total_withheld.0, DEFAULT_MIN_DEPOSIT);
+        assert_eq!(stats.total_keeper_fees.0, keeper_fee);
+        assert_eq!(stats.available_withheld.0, DEFAULT_MIN_DEPOSIT - keeper_fee);
+
+        assert_eq!(received_contract.settle_expired_tasks(account, None, None), 0);
+    }
+
+    #[test]
+    fn check_settlement_of_page_of_tasks() {
+        let mut context = get_context(to_valid_account("lrn.testnet"));
+        testing_env!(context.attached_deposit(DEFAULT_MIN_DEPOSIT).build());
+        let account = context.build().predecessor_account_id;
+        let mut received_contract = Contract::new(to_valid_account(OWNER), Config::default());
+        for _ in 0..3 {
+            received_contract.create_task("expired task".to_string(), deadline(1658179621), None, None);
+        }
+
+        testing_env!(context
+            .predecessor_account_id(to_valid_account("keeper.testnet"))
+            .attached_deposit(0)
+            .block_timestamp(AFTER_DEADLINE)
+            .build());
+        assert_eq!(received_contract.settle_expired_tasks(account.clone(), Some(2), Some(1)), 1);
+
+        let user_records = received_contract.get_user_records(&account).unwrap();
+        assert_eq!(user_records.get_record(1).unwrap().deposit_status, DepositStatus::Contributed);
+        assert_eq!(user_records.get_record(2).unwrap().deposit_status, DepositStatus::Withheld);
+        assert_eq!(user_records.get_record(3).unwrap().deposit_status, DepositStatus::Contributed);
+    }
+
+    #[test]
+    fn check_settlement_of_own_tasks() {
+        let mut context = get_context(to_valid_account("lrn.testnet"));
+        testing_env!(context.attached_deposit(DEFAULT_MIN_DEPOSIT).build());
+        let account = context.build().predecessor_account_id;
+        let mut received_contract = Contract::new(to_valid_account(OWNER), Config::default());
+        received_contract.create_task("expired task".to_string(), deadline(1658179621), None, None);
+
+        testing_env!(context.attached_deposit(0).block_timestamp(AFTER_DEADLINE).build());
+        assert_eq!(received_contract.settle_expired_tasks(account, None, None), 1);
+
+        let stats = received_contract.get_treasury_stats();
+        assert_eq!(stats.total_keeper_fees.0, 0);
+        assert_eq!(stats.available_withheld.0, DEFAULT_MIN_DEPOSIT);
+        assert!(get_created_receipts().is_empty());
+        assert!(get_logs().iter().any(|log| log.contains(r#""event":"task_settled""#)
+            && log.contains(r#""keeper_fee":"0""#)));
+    }
+
+    #[test]
+    fn check_completion_of_settled_task() {
+        let mut context = get_context(to_valid_account("lrn.testnet"));
+        testing_env!(context.attached_deposit(DEFAULT_MIN_DEPOSIT).build());
+        let account = context.build().predecessor_account_id;
+        let mut received_contract = Contract::new(to_valid_account(OWNER), Config::default());
+
+        received_contract.create_task("default task".to_string(), deadline(1658179621), None, None);
+        testing_env!(context.block_timestamp(AFTER_DEADLINE).build());
+        received_contract.settle_expired_tasks(account, None, None);
+        received_contract.make_complete_task_status(FIRST_TASK);
+
+        assert_eq!(received_contract.get_treasury_stats().total_withheld.0, DEFAULT_MIN_DEPOSIT);
+    }
+
+    #[test]
+    fn check_claiming_of_refunds() {
+        let mut context = get_context(to_valid_account("lrn.testnet"));
+        testing_env!(context.attached_deposit(DEFAULT_MIN_DEPOSIT).build());
+        let account = context.build().predecessor_account_id;
+        let mut received_contract = Contract::new(to_valid_account(OWNER), Config::default());
+
+        received_contract.create_task("default task".to_string(), deadline(1658179621), None, None);
+        received_contract.create_task("default task".to_string(), deadline(1658179621), None, None);
+        received_contract.make_complete_task_status(FIRST_TASK);
+        received_contract.make_complete_task_status(2);
+
+        let record = received_contract.get_task_by_id(FIRST_TASK, account.clone());
+        assert_eq!(record.deposit_status, DepositStatus::Refunded);
+        assert_eq!(received_contract.get_claimable(account.clone()).0, 2 * DEFAULT_MIN_DEPOSIT);
+
+        testing_env!(context.attached_deposit(0).build());
+        received_contract.claim_refunds(Some(to_valid_account("friend.testnet")));
+
+        assert_eq!(received_contract.get_claimable(account).0, 0);
+        let stats = received_contract.get_treasury_stats();
+        assert_eq!(stats.total_refunded.0, 2 * DEFAULT_MIN_DEPOSIT);
+        assert_eq!(stats.total_claimed.0, 2 * DEFAULT_MIN_DEPOSIT);
+    }
+
+    #[test]
+    fn check_failed_claiming_of_refunds() {
+        let mut context = get_context(to_valid_account("lrn.testnet"));
+        testing_env!(context.attached_deposit(DEFAULT_MIN_DEPOSIT).build());
+        let account = context.build().predecessor_account_id;
+        let mut received_contract = Contract::new(to_valid_account(OWNER), Config::default());
+
+        received_contract.create_task("default task".to_string(), deadline(1658179621), None, None);
+        received_contract.make_complete_task_status(FIRST_TASK);
+        testing_env!(context.attached_deposit(0).build());
+        received_contract.claim_refunds(None);
+
+        testing_env!(
+            context.build(),
+            VMConfig::test(),
+            RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Failed]
+        );
+        received_contract.on_claim_refunds_complete(account.clone(), U128(DEFAULT_MIN_DEPOSIT));
+
+        assert_eq!(received_contract.get_claimable(account).0, DEFAULT_MIN_DEPOSIT);
+        assert_eq!(received_contract.get_treasury_stats().total_claimed.0, 0);
+    }
+
+    #[test]
+    fn check_normalization_of_deadlines() {
+        let mut context = get_context(to_valid_account("lrn.testnet"));
+        testing_env!(context.attached_deposit(DEFAULT_MIN_DEPOSIT).build());
+        let mut received_contract = Contract::new(to_valid_account(OWNER), Config::default());
+
+        let expected = U64(1_658_179_621_000_000_000);
+        let deadlines = vec![
+            Deadline::Nanoseconds(expected),
+            Deadline::Format(DeadlineFormat::Seconds(1_658_179_621)),
+            Deadline::Format(DeadlineFormat::Milliseconds(1_658_179_621_000)),
+            Deadline::Format(DeadlineFormat::Iso8601("2022-07-18T21:27:01Z".to_string())),
+            Deadline::Format(DeadlineFormat::Iso8601("2022-07-19T00:27:01+03:00".to_string())),
+            Deadline::Format(DeadlineFormat::Relative("2d1h53m41s".to_string())),
+        ];
+        for deadline_time in deadlines {
+            assert_eq!(received_contract.create_task("default task".to_string(), deadline_time, None, None), expected);
+        }
+
+        assert_eq!(
+            crate::deadline::parse_iso8601("2022-07-18T21:27").unwrap(),
+            1_658_179_620_000_000_000
+        );
+        assert_eq!(crate::deadline::parse_iso8601("2024-02-29").unwrap(), 1_709_164_800_000_000_000);
+        assert!(crate::deadline::parse_iso8601("2023-02-29").is_none());
+        assert!(crate::deadline::parse_iso8601("2022-07-18T21:27:01Q").is_none());
+        assert!(crate::deadline::parse_duration("3").is_none());
+        assert!(crate::deadline::parse_duration("3y").is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "Deadline is in the past")]
+    fn check_creation_of_task_with_deadline_in_past() {
+        let mut context = get_context(to_valid_account("lrn.testnet"));
+        testing_env!(context.attached_deposit(DEFAULT_MIN_DEPOSIT).build());
+        let mut received_contract = Contract::new(to_valid_account(OWNER), Config::default());
+
+        received_contract.create_task("default task".to_string(), deadline(1_657_000_000), None, None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Deadline is too far in the future")]
+    fn check_creation_of_task_with_deadline_beyond_horizon() {
+        let mut context = get_context(to_valid_account("lrn.testnet"));
+        testing_env!(context.attached_deposit(DEFAULT_MIN_DEPOSIT).build());
+        let mut received_contract = Contract::new(to_valid_account(OWNER), Config::default());
+
+        received_contract.create_task(
+            "default task".to_string(),
+            Deadline::Format(DeadlineFormat::Relative("53w".to_string())),
+            None,
+            None,
+        );
+    }
+
+    #[test]
+    fn check_events_of_task_lifecycle() {
+        let mut context = get_context(to_valid_account("lrn.testnet"));
+        testing_env!(context.attached_deposit(DEFAULT_MIN_DEPOSIT).build());
+        let mut received_contract = Contract::new(to_valid_account(OWNER), Config::default());
+
+        received_contract.create_task("default task".to_string(), deadline(1658179621), None, None);
+        received_contract.make_complete_task_status(FIRST_TASK);
+
+        assert_eq!(
+            get_logs(),
+            vec![
+                r#"EVENT_JSON:{"standard":"dear_procrastination","version":"1.0.0","event":"task_created","data":[{"account_id":"lrn.testnet","record_id":1,"deadline_time":"1658179621000000000","deposit":"3000000000000000000000000"}]}"#,
+                r#"EVENT_JSON:{"standard":"dear_procrastination","version":"1.0.0","event":"task_completed","data":[{"account_id":"lrn.testnet","record_id":1,"on_time":true}]}"#,
+                r#"EVENT_JSON:{"standard":"dear_procrastination","version":"1.0.0","event":"deposit_refunded","data":[{"account_id":"lrn.testnet","record_id":1,"amount":"3000000000000000000000000"}]}"#,
+            ]
+        );
+    }
+
+    #[test]
+    fn check_events_of_settlement() {
+        let mut context = get_context(to_valid_account("lrn.testnet"));
+        testing_env!(context.attached_deposit(DEFAULT_MIN_DEPOSIT).build());
+        let account = context.build().predecessor_account_id;
+        let mut received_contract = Contract::new(to_valid_account(OWNER), Config::default());
+
+        received_contract.create_task("default task".to_string(), deadline(1658179621), None, None);
+        testing_env!(context
+            .predecessor_account_id(to_valid_account("keeper.testnet"))
+            .attached_deposit(0)
+            .block_timestamp(AFTER_DEADLINE)
+            .build());
+        received_contract.settle_expired_tasks(account, None, None);
+
+        assert_eq!(
+            get_logs(),
+            vec![
+                r#"EVENT_JSON:{"standard":"dear_procrastination","version":"1.0.0","event":"deposit_withheld","data":[{"account_id":"lrn.testnet","record_id":1,"amount":"3000000000000000000000000"}]}"#,
+                r#"EVENT_JSON:{"standard":"dear_procrastination","version":"1.0.0","event":"task_settled","data":[{"account_id":"lrn.testnet","record_id":1,"keeper_id":"keeper.testnet","keeper_fee":"30000000000000000000000"}]}"#,
+            ]
+        );
+    }
+
+    #[test]
+    fn check_editing_of_task() {
+        let mut context = get_context(to_valid_account("lrn.testnet"));
+        testing_env!(context.attached_deposit(DEFAULT_MIN_DEPOSIT).build());
+        let mut received_contract = Contract::new(to_valid_account(OWNER), Config::default());
+
+        received_contract.create_task("defualt task".to_string(), deadline(1658179621), None, None);
+        testing_env!(context.attached_deposit(0).build());
+        let record = received_contract.update_task(
+            FIRST_TASK,
+            Some("default task".to_string()),
+            Some(deadline(1658179600)),
+        );
+
+        assert_eq!(record.task, "default task");
+        assert_eq!(record.deadline_time, 1658179600 * NANOS_IN_SECOND);
+        assert_eq!(record.guarantee_of_task_completion, DEFAULT_MIN_DEPOSIT);
+        assert_eq!(record.history.len(), 2);
+        assert!(matches!(&record.history[0].change, TaskChange::Task { old_task } if old_task == "defualt task"));
+        assert!(matches!(
+            record.history[1].change,
+            TaskChange::Deadline { old_deadline_time: 1658179621000000000, fee: 0 }
+        ));
+    }
+
+    #[test]
+    fn check_extension_of_deadline_with_penalty() {
+        let mut context = get_context(to_valid_account("lrn.testnet"));
+        testing_env!(context.attached_deposit(DEFAULT_MIN_DEPOSIT).build());
+        let mut received_contract = Contract::new(to_valid_account(OWNER), Config::default());
+
+        received_contract.create_task("default task".to_string(), deadline(1658179621), None, None);
+        testing_env!(context.attached_deposit(0).build());
+        let record = received_contract.update_task(FIRST_TASK, None, Some(deadline(1658279621)));
+
+        let penalty = DEFAULT_MIN_DEPOSIT / 10;
+        assert_eq!(record.guarantee_of_task_completion, DEFAULT_MIN_DEPOSIT - penalty);
+        assert_eq!(received_contract.get_treasury_stats().total_withheld.0, penalty);
+        assert_eq!(
+            received_contract.get_treasury_stats().outstanding_deposits.0,
+            DEFAULT_MIN_DEPOSIT - penalty
+        );
+    }
+
+    #[test]
+    fn check_extension_of_deadline_with_extra_deposit() {
+        let mut context = get_context(to_valid_account("lrn.testnet"));
+        testing_env!(context.attached_deposit(DEFAULT_MIN_DEPOSIT).build());
+        let config = Config {
+            extension_policy: ExtensionPolicy::ExtraDeposit,
+            ..Config::default()
+        };
+        let mut received_contract = Contract::new(to_valid_account(OWNER), config);
+
+        received_contract.create_task("default task".to_string(), deadline(1658179621), None, None);
+        let extra_deposit = DEFAULT_MIN_DEPOSIT / 10;
+        testing_env!(context.attached_deposit(extra_deposit).build());
+        let record = received_contract.update_task(FIRST_TASK, None, Some(deadline(1658279621)));
+
+        assert_eq!(record.guarantee_of_task_completion, DEFAULT_MIN_DEPOSIT + extra_deposit);
+        assert_eq!(
+            received_contract.get_treasury_stats().total_contributed.0,
+            DEFAULT_MIN_DEPOSIT + extra_deposit
+        );
+        let stats = received_contract.get_user_stats(to_valid_account("lrn.testnet"));
+        assert_eq!(stats.tasks_created, 1);
+        assert_eq!(stats.total_staked.0, DEFAULT_MIN_DEPOSIT + extra_deposit);
+    }
+
+    #[test]
+    #[should_panic(expected = "Attached deposit must be")]
+    fn check_extension_of_deadline_without_extra_deposit() {
+        let mut context = get_context(to_valid_account("lrn.testnet"));
+        testing_env!(context.attached_deposit(DEFAULT_MIN_DEPOSIT).build());
+        let config = Config {
+            extension_policy: ExtensionPolicy::ExtraDeposit,
+            ..Config::default()
+        };
+        let mut received_contract = Contract::new(to_valid_account(OWNER), config);
+
+        received_contract.create_task("default task".to_string(), deadline(1658179621), None, None);
+        testing_env!(context.attached_deposit(0).build());
+        received_contract.update_task(FIRST_TASK, None, Some(deadline(1658279621)));
+    }
+
+    #[test]
+    fn check_cancellation_of_task() {
+        let mut context = get_context(to_valid_account("lrn.testnet"));
+        testing_env!(context.attached_deposit(DEFAULT_MIN_DEPOSIT).build());
+        let account = context.build().predecessor_account_id;
+        let mut received_contract = Contract::new(to_valid_account(OWNER), Config::default());
+
+        received_contract.create_task("default task".to_string(), deadline(1658179621), None, None);
+        testing_env!(context.attached_deposit(0).build());
+        let refund = received_contract.cancel_task(FIRST_TASK);
+
+        let fee = DEFAULT_MIN_DEPOSIT / 20;
+        assert_eq!(refund.0, DEFAULT_MIN_DEPOSIT - fee);
+        assert_eq!(received_contract.get_claimable(account.clone()).0, DEFAULT_MIN_DEPOSIT - fee);
+        let record = received_contract.get_task_by_id(FIRST_TASK, account);
+        assert_eq!(record.deposit_status, DepositStatus::Cancelled);
+        assert_eq!(record.history.len(), 1);
+
+        let stats = received_contract.get_treasury_stats();
+        assert_eq!(stats.total_withheld.0, fee);
+        assert_eq!(stats.outstanding_deposits.0, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Task can be cancelled only within")]
+    fn check_cancellation_after_cooldown() {
+        let mut context = get_context(to_valid_account("lrn.testnet"));
+        testing_env!(context.attached_deposit(DEFAULT_MIN_DEPOSIT).build());
+        let mut received_contract = Contract::new(to_valid_account(OWNER), Config::default());
+
+        received_contract.create_task("default task".to_string(), deadline(1658179621), None, None);
+        testing_env!(context.attached_deposit(0).block_timestamp(NOW + 2 * 86_400 * NANOS_IN_SECOND).build());
+        received_contract.cancel_task(FIRST_TASK);
+    }
+
+    #[test]
+    fn check_creation_of_task_with_token() {
+        let context = get_context(to_valid_account(TOKEN));
+        testing_env!(context.build());
+        let account = to_valid_account("lrn.testnet");
+        let mut received_contract = Contract::new(to_valid_account(OWNER), token_config());
+
+        let unused = received_contract.ft_on_transfer(
+            account.clone(),
+            U128(TOKEN_MIN_DEPOSIT),
+            token_msg(1658179621),
+        );
+
+        assert!(matches!(unused, near_sdk::PromiseOrValue::Value(U128(0))));
+        let record = received_contract.get_task_by_id(FIRST_TASK, account);
+        assert_eq!(record.guarantee_of_task_completion, TOKEN_MIN_DEPOSIT);
+        assert_eq!(record.token_id, Some(to_valid_account(TOKEN)));
+        let stats = received_contract.get_token_treasury_stats(to_valid_account(TOKEN));
+        assert_eq!(stats.total_contributed.0, TOKEN_MIN_DEPOSIT);
+        assert_eq!(received_contract.get_treasury_stats().total_contributed.0, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Token is not accepted as a deposit")]
+    fn check_creation_of_task_with_unknown_token() {
+        let context = get_context(to_valid_account("fake.testnet"));
+        testing_env!(context.build());
+        let mut received_contract = Contract::new(to_valid_account(OWNER), token_config());
+
+        received_contract.ft_on_transfer(
+            to_valid_account("lrn.testnet"),
+            U128(TOKEN_MIN_DEPOSIT),
+            token_msg(1658179621),
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "For creation task you need pay minimum")]
+    fn check_min_token_deposit_for_creation_task() {
+        let context = get_context(to_valid_account(TOKEN));
+        testing_env!(context.build());
+        let mut received_contract = Contract::new(to_valid_account(OWNER), token_config());
+
+        received_contract.ft_on_transfer(
+            to_valid_account("lrn.testnet"),
+            U128(TOKEN_MIN_DEPOSIT - 1),
+            token_msg(1658179621),
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "For creation task you can pay maximum")]
+    fn check_max_token_deposit_for_creation_task() {
+        let context = get_context(to_valid_account(TOKEN));
+        testing_env!(context.build());
+        let mut config = token_config();
+        config
+            .token_max_deposits
+            .insert(to_valid_account(TOKEN), U128(2 * TOKEN_MIN_DEPOSIT));
+        let mut received_contract = Contract::new(to_valid_account(OWNER), config);
+
+        received_contract.ft_on_transfer(
+            to_valid_account("lrn.testnet"),
+            U128(2 * TOKEN_MIN_DEPOSIT),
+            token_msg(1658179621),
+        );
+        received_contract.ft_on_transfer(
+            to_valid_account("lrn.testnet"),
+            U128(2 * TOKEN_MIN_DEPOSIT + 1),
+            token_msg(1658179621),
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Maximal deposit is set for a token that is not accepted")]
+    fn check_max_deposit_of_unknown_token() {
+        let context = get_context(to_valid_account(OWNER));
+        testing_env!(context.build());
+        let mut received_contract = Contract::new(to_valid_account(OWNER), Config::default());
+
+        let mut config = Config::default();
+        config
+            .token_max_deposits
+            .insert(to_valid_account(TOKEN), U128(TOKEN_MIN_DEPOSIT));
+        received_contract.set_config(config);
+    }
+
+    #[test]
+    fn check_claiming_of_token_refunds() {
+        let mut context = get_context(to_valid_account(TOKEN));
+        testing_env!(context.build());
+        let account = to_valid_account("lrn.testnet");
+        let token = to_valid_account(TOKEN);
+        let mut received_contract = Contract::new(to_valid_account(OWNER), token_config());
+
+        received_contract.ft_on_transfer(account.clone(), U128(TOKEN_MIN_DEPOSIT), token_msg(1658179621));
+        testing_env!(context.predecessor_account_id(account.clone()).build());
+        received_contract.make_complete_task_status(FIRST_TASK);
+
+        assert_eq!(received_contract.get_claimable(account.clone()).0, 0);
+        assert_eq!(
+            received_contract.get_claimable_tokens(account.clone()).get(&token),
+            Some(&U128(TOKEN_MIN_DEPOSIT))
+        );
+
+        testing_env!(context.attached_deposit(1).build());
+        received_contract.claim_token_refunds(token.clone(), None);
+        assert!(received_contract.get_claimable_tokens(account.clone()).is_empty());
+
+        testing_env!(
+            context.build(),
+            VMConfig::test(),
+            RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Failed]
+        );
+        received_contract.on_claim_token_refunds_complete(
+            account.clone(),
+            token.clone(),
+            U128(TOKEN_MIN_DEPOSIT),
+        );
+
+        assert_eq!(
+            received_contract.get_claimable_tokens(account).get(&token),
+            Some(&U128(TOKEN_MIN_DEPOSIT))
+        );
+        let stats = received_contract.get_token_treasury_stats(token);
+        assert_eq!(stats.total_refunded.0, TOKEN_MIN_DEPOSIT);
+        assert_eq!(stats.total_claimed.0, 0);
+    }
+
+    #[test]
+    fn check_settlement_of_task_with_token() {
+        let mut context = get_context(to_valid_account(TOKEN));
+        testing_env!(context.build());
+        let account = to_valid_account("lrn.testnet");
+        let mut received_contract = Contract::new(to_valid_account(OWNER), token_config());
+
+        received_contract.ft_on_transfer(account.clone(), U128(TOKEN_MIN_DEPOSIT), token_msg(1658179621));
+        testing_env!(context
+            .predecessor_account_id(to_valid_account("keeper.testnet"))
+            .block_timestamp(AFTER_DEADLINE)
+            .build());
+
+        assert_eq!(received_contract.settle_expired_tasks(account, None, None), 1);
+        let stats = received_contract.get_token_treasury_stats(to_valid_account(TOKEN));
+        assert_eq!(stats.total_withheld.0, TOKEN_MIN_DEPOSIT);
+        assert_eq!(stats.total_keeper_fees.0, 0);
+        assert_eq!(received_contract.get_treasury_stats().total_keeper_fees.0, 0);
+    }
+
+    fn create_verified_task(context: &mut VMContextBuilder) -> Contract {
+        testing_env!(context.attached_deposit(DEFAULT_MIN_DEPOSIT).build());
+        let mut received_contract = Contract::new(to_valid_account(OWNER), Config::default());
+        received_contract.create_task(
+            "default task".to_string(),
+            deadline(1658179621),
+            Some(to_valid_account("partner.testnet")),
+            None,
+        );
+        testing_env!(context.attached_deposit(0).build());
+        received_contract.claim_completion(FIRST_TASK, "https://example.com/proof".to_string());
+        received_contract
+    }
+
+    #[test]
+    #[should_panic(expected = "Completion of the task has to be confirmed by the verifier")]
+    fn check_completion_of_verified_task_without_verifier() {
+        let mut context = get_context(to_valid_account("lrn.testnet"));
+        testing_env!(context.attached_deposit(DEFAULT_MIN_DEPOSIT).build());
+        let mut received_contract = Contract::new(to_valid_account(OWNER), Config::default());
+
+        received_contract.create_task(
+            "default task".to_string(),
+            deadline(1658179621),
+            Some(to_valid_account("partner.testnet")),
+            None,
+        );
+        received_contract.make_complete_task_status(FIRST_TASK);
+    }
+
+    #[test]
+    fn check_approval_of_completion() {
+        let mut context = get_context(to_valid_account("lrn.testnet"));
+        let account = context.build().predecessor_account_id;
+        let mut received_contract = create_verified_task(&mut context);
+
+        // The verifier answers after the deadline, the claim was submitted in time
+        testing_env!(context
+            .predecessor_account_id(to_valid_account("partner.testnet"))
+            .block_timestamp(AFTER_DEADLINE)
+            .build());
+        assert_eq!(received_contract.settle_expired_tasks(account.clone(), None, None), 0);
+        received_contract.approve_completion(account.clone(), FIRST_TASK);
+
+        let record = received_contract.get_task_by_id(FIRST_TASK, account.clone());
+        assert!(record.is_complete_status);
+        assert_eq!(record.deposit_status, DepositStatus::Refunded);
+        assert_eq!(record.completion_claim.unwrap().status, ClaimStatus::Approved);
+        assert_eq!(received_contract.get_claimable(account).0, DEFAULT_MIN_DEPOSIT);
+    }
+
+    #[test]
+    fn check_rejection_of_completion() {
+        let mut context = get_context(to_valid_account("lrn.testnet"));
+        let account = context.build().predecessor_account_id;
+        let mut received_contract = create_verified_task(&mut context);
+
+        testing_env!(context
+            .predecessor_account_id(to_valid_account("partner.testnet"))
+            .build());
+        received_contract.reject_completion(account.clone(), FIRST_TASK);
+
+        let record = received_contract.get_task_by_id(FIRST_TASK, account.clone());
+        assert!(!record.is_complete_status);
+        assert_eq!(record.deposit_status, DepositStatus::Withheld);
+        assert_eq!(record.completion_claim.unwrap().status, ClaimStatus::Rejected);
+        assert_eq!(received_contract.get_claimable(account).0, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the verifier of the task can call this method")]
+    fn check_approval_by_not_verifier() {
+        let mut context = get_context(to_valid_account("lrn.testnet"));
+        let account = context.build().predecessor_account_id;
+        let mut received_contract = create_verified_task(&mut context);
+
+        received_contract.approve_completion(account, FIRST_TASK);
+    }
+
+    #[test]
+    fn check_resolution_of_expired_claim() {
+        let mut context = get_context(to_valid_account("lrn.testnet"));
+        let account = context.build().predecessor_account_id;
+        let mut received_contract = create_verified_task(&mut context);
+
+        testing_env!(context
+            .predecessor_account_id(to_valid_account("keeper.testnet"))
+            .block_timestamp(NOW + received_contract.get_config().verification_timeout.0)
+            .build());
+        received_contract.resolve_expired_claim(account.clone(), FIRST_TASK);
+
+        let record = received_contract.get_task_by_id(FIRST_TASK, account.clone());
+        assert_eq!(record.deposit_status, DepositStatus::Refunded);
+        assert_eq!(received_contract.get_claimable(account).0, DEFAULT_MIN_DEPOSIT);
+        assert_eq!(received_contract.get_treasury_stats().outstanding_deposits.0, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Completion of the task is waiting for the verifier")]
+    fn check_completion_of_task_with_pending_claim_after_deadline() {
+        let mut context = get_context(to_valid_account("lrn.testnet"));
+        let account = context.build().predecessor_account_id;
+        let mut received_contract = create_verified_task(&mut context);
+
+        // Completing the task after the deadline would withhold the deposit
+        // that the pending claim can still refund
+        testing_env!(context.block_timestamp(AFTER_DEADLINE).build());
+        received_contract.make_complete_task_status(FIRST_TASK);
+
+        testing_env!(context
+            .block_timestamp(NOW + received_contract.get_config().verification_timeout.0)
+            .build());
+        received_contract.resolve_expired_claim(account, FIRST_TASK);
+    }
+
+    #[test]
+    #[should_panic(expected = "Verifier can still answer the claim")]
+    fn check_resolution_of_claim_before_timeout() {
+        let mut context = get_context(to_valid_account("lrn.testnet"));
+        let account = context.build().predecessor_account_id;
+        let mut received_contract = create_verified_task(&mut context);
+
+        received_contract.resolve_expired_claim(account, FIRST_TASK);
+    }
+
+    #[test]
+    fn check_completion_of_unverified_task_with_proof_after_deadline() {
+        let mut context = get_context(to_valid_account("lrn.testnet"));
+        testing_env!(context.attached_deposit(DEFAULT_MIN_DEPOSIT).build());
+        let account = context.build().predecessor_account_id;
+        let mut received_contract = Contract::new(to_valid_account(OWNER), Config::default());
+
+        received_contract.create_task("default task".to_string(), deadline(1658179621), None, None);
+        testing_env!(context.attached_deposit(0).build());
+        received_contract.submit_proof(
+            FIRST_TASK,
+            "https://github.com/vadimlarintech/dear-procrastination/commit/49ece53".to_string(),
+            "49ece53".to_string(),
+        );
+
+        // Nobody checks the proof of a task without a verifier, the deposit is withheld anyway
+        testing_env!(context.block_timestamp(AFTER_DEADLINE).build());
+        received_contract.make_complete_task_status(FIRST_TASK);
+
+        let record = received_contract.get_task_by_id(FIRST_TASK, account.clone());
+        assert_eq!(record.deposit_status, DepositStatus::Withheld);
+        assert_eq!(record.proofs.len(), 1);
+        assert_eq!(record.proofs[0].hash, "49ece53");
+        assert_eq!(record.proofs[0].submitted_at, NOW);
+        assert_eq!(received_contract.get_claimable(account).0, 0);
+    }
+
+    #[test]
+    fn check_settlement_of_verified_task_with_proof() {
+        let mut context = get_context(to_valid_account("lrn.testnet"));
+        testing_env!(context.attached_deposit(DEFAULT_MIN_DEPOSIT).build());
+        let account = context.build().predecessor_account_id;
+        let mut received_contract = Contract::new(to_valid_account(OWNER), Config::default());
+
+        received_contract.create_task(
+            "default task".to_string(),
+            deadline(1658179621),
+            Some(to_valid_account("partner.testnet")),
+            None,
+        );
+        testing_env!(context.attached_deposit(0).build());
+        received_contract.submit_proof(FIRST_TASK, "ipfs://bafybeigdyrzt".to_string(), "bafybeigdyrzt".to_string());
+
+        testing_env!(context.block_timestamp(AFTER_DEADLINE).build());
+        assert_eq!(received_contract.settle_expired_tasks(account, None, None), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Deadline was ended, proof can not be submitted")]
+    fn check_submission_of_proof_after_deadline() {
+        let mut context = get_context(to_valid_account("lrn.testnet"));
+        testing_env!(context.attached_deposit(DEFAULT_MIN_DEPOSIT).build());
+        let mut received_contract = Contract::new(to_valid_account(OWNER), Config::default());
+
+        received_contract.create_task("default task".to_string(), deadline(1658179621), None, None);
+        testing_env!(context.attached_deposit(0).block_timestamp(AFTER_DEADLINE).build());
+        received_contract.submit_proof(FIRST_TASK, "".to_string(), "49ece53".to_string());
+    }
+
+    #[test]
+    fn check_claim_of_verified_task_with_proof_after_deadline() {
+        let mut context = get_context(to_valid_account("lrn.testnet"));
+        testing_env!(context.attached_deposit(DEFAULT_MIN_DEPOSIT).build());
+        let account = context.build().predecessor_account_id;
+        let mut received_contract = Contract::new(to_valid_account(OWNER), Config::default());
+
+        received_contract.create_task(
+            "default task".to_string(),
+            deadline(1658179621),
+            Some(to_valid_account("partner.testnet")),
+            None,
+        );
+        testing_env!(context.attached_deposit(0).build());
+        received_contract.submit_proof(FIRST_TASK, "ipfs://bafybeigdyrzt".to_string(), "bafybeigdyrzt".to_string());
+
+        testing_env!(context.block_timestamp(AFTER_DEADLINE).build());
+        received_contract.claim_completion(FIRST_TASK, "bafybeigdyrzt".to_string());
+        testing_env!(context
+            .predecessor_account_id(to_valid_account("partner.testnet"))
+            .build());
+        received_contract.approve_completion(account.clone(), FIRST_TASK);
+
+        let record = received_contract.get_task_by_id(FIRST_TASK, account);
+        assert_eq!(record.deposit_status, DepositStatus::Refunded);
+    }
+
+    #[test]
+    #[should_panic(expected = "Verifier of the task can not be its beneficiary")]
+    fn check_creation_of_task_verified_by_beneficiary() {
+        let mut context = get_context(to_valid_account("lrn.testnet"));
+        testing_env!(context.attached_deposit(DEFAULT_MIN_DEPOSIT).build());
+        let mut received_contract = Contract::new(to_valid_account(OWNER), Config::default());
+
+        received_contract.create_task(
+            "default task".to_string(),
+            deadline(1658179621),
+            Some(to_valid_account("charity.testnet")),
+            Some(to_valid_account("charity.testnet")),
+        );
+    }
+
+    #[test]
+    fn check_donation_of_withheld_deposit() {
+        let mut context = get_context(to_valid_account("lrn.testnet"));
+        testing_env!(context.attached_deposit(DEFAULT_MIN_DEPOSIT).build());
+        let account = context.build().predecessor_account_id;
+        let mut received_contract = Contract::new(to_valid_account(OWNER), Config::default());
+
+        received_contract.create_task(
+            "default task".to_string(),
+            deadline(1658179621),
+            None,
+            Some(to_valid_account("rival.testnet")),
+        );
+        testing_env!(context
+            .predecessor_account_id(to_valid_account("keeper.testnet"))
+            .attached_deposit(0)
+            .block_timestamp(AFTER_DEADLINE)
+            .build());
+        received_contract.settle_expired_tasks(account, None, None);
+
+        let keeper_fee = DEFAULT_MIN_DEPOSIT / 100;
+        let platform_fee = DEFAULT_MIN_DEPOSIT / 20;
+        let stats = received_contract.get_treasury_stats();
+        assert_eq!(stats.total_withheld.0, DEFAULT_MIN_DEPOSIT);
+        assert_eq!(stats.total_donated.0, DEFAULT_MIN_DEPOSIT - keeper_fee - platform_fee);
+        assert_eq!(stats.available_withheld.0, platform_fee);
+        assert!(get_logs().iter().any(|log| log.contains(r#""event":"deposit_donated""#)
+            && log.contains(r#""beneficiary_id":"rival.testnet""#)));
+
+        testing_env!(
+            context.build(),
+            VMConfig::test(),
+            RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Failed]
+        );
+        received_contract.on_donation_complete(None, stats.total_donated);
+
+        let stats = received_contract.get_treasury_stats();
+        assert_eq!(stats.total_donated.0, 0);
+        assert_eq!(stats.available_withheld.0, DEFAULT_MIN_DEPOSIT - keeper_fee);
+    }
+
+    #[test]
+    fn check_donations_of_settlement_batch() {
+        let mut context = get_context(to_valid_account("lrn.testnet"));
+        let account = context.build().predecessor_account_id;
+        testing_env!(context.attached_deposit(DEFAULT_MIN_DEPOSIT).build());
+        let mut received_contract = Contract::new(to_valid_account(OWNER), Config::default());
+
+        let tasks = MAX_DONATIONS_PER_CALL + 2;
+        for task in 0..tasks {
+            testing_env!(context.build());
+            received_contract.create_task(
+                "default task".to_string(),
+                deadline(1658179621),
+                None,
+                Some(to_valid_account(&format!("charity{}.testnet", task))),
+            );
+        }
+
+        testing_env!(context
+            .predecessor_account_id(to_valid_account("keeper.testnet"))
+            .attached_deposit(0)
+            .block_timestamp(AFTER_DEADLINE)
+            .build());
+        let settled = received_contract.settle_expired_tasks(account.clone(), None, None);
+        assert_eq!(settled as usize, MAX_DONATIONS_PER_CALL);
+        // A transfer with its callback per beneficiary and per the keeper fee
+        assert_eq!(get_created_receipts().len(), 2 * (MAX_DONATIONS_PER_CALL + 1));
+
+        testing_env!(context.build());
+        let from_index = Some(MAX_DONATIONS_PER_CALL as u64 + 1);
+        assert_eq!(received_contract.settle_expired_tasks(account, from_index, None), 2);
+    }
+
+    #[test]
+    fn check_donations_to_same_beneficiary() {
+        let mut context = get_context(to_valid_account("lrn.testnet"));
+        let account = context.build().predecessor_account_id;
+        testing_env!(context.attached_deposit(DEFAULT_MIN_DEPOSIT).build());
+        let mut received_contract = Contract::new(to_valid_account(OWNER), Config::default());
+
+        let tasks = MAX_DONATIONS_PER_CALL as u64 + 2;
+        for _ in 0..tasks {
+            testing_env!(context.build());
+            received_contract.create_task(
+                "default task".to_string(),
+                deadline(1658179621),
+                None,
+                Some(to_valid_account("charity.testnet")),
+            );
+        }
+
+        testing_env!(context
+            .predecessor_account_id(to_valid_account("keeper.testnet"))
+            .attached_deposit(0)
+            .block_timestamp(AFTER_DEADLINE)
+            .build());
+        let settled = received_contract.settle_expired_tasks(account, None, Some(tasks));
+        assert_eq!(settled as u64, tasks);
+        assert_eq!(get_created_receipts().len(), 4);
+
+        let keeper_fee = DEFAULT_MIN_DEPOSIT / 100;
+        let platform_fee = DEFAULT_MIN_DEPOSIT / 20;
+        let stats = received_contract.get_treasury_stats();
+        assert_eq!(stats.total_donated.0, tasks as u128 * (DEFAULT_MIN_DEPOSIT - keeper_fee - platform_fee));
+    }
+
+    #[test]
+    #[should_panic(expected = "Beneficiary must be one of the charities of the config")]
+    fn check_beneficiary_outside_of_charities() {
+        let mut context = get_context(to_valid_account("lrn.testnet"));
+        testing_env!(context.attached_deposit(DEFAULT_MIN_DEPOSIT).build());
+        let config = Config {
+            charities: vec![to_valid_account("charity.testnet")],
+            beneficiary_whitelist_only: true,
+            ..Config::default()
+        };
+        let mut received_contract = Contract::new(to_valid_account(OWNER), config);
+
+        received_contract.create_task(
+            "default task".to_string(),
+            deadline(1658179621),
+            None,
+            Some(to_valid_account("rival.testnet")),
+        );
+    }
+
+    #[test]
+    fn check_recurring_task() {
+        let mut context = get_context(to_valid_account("lrn.testnet"));
+        testing_env!(context.attached_deposit(3 * DEFAULT_MIN_DEPOSIT).build());
+        let account = context.build().predecessor_account_id;
+        let mut received_contract = Contract::new(to_valid_account(OWNER), Config::default());
+        let day = 86_400 * NANOS_IN_SECOND;
+
+        let recurring_id = received_contract.create_recurring_task(
+            "standup".to_string(),
+            Schedule::Daily,
+            U128(DEFAULT_MIN_DEPOSIT),
+            3,
+        );
+        assert_eq!(received_contract.get_user_task_count(account.clone(), None), 0);
+        assert_eq!(received_contract.get_treasury_stats().total_contributed.0, 3 * DEFAULT_MIN_DEPOSIT);
+
+        // The first day is missed, the habit is done on the second day
+        testing_env!(context.attached_deposit(0).block_timestamp(NOW + day + 1).build());
+        let record_id = received_contract.complete_recurring_task(recurring_id);
+
+        let tasks = received_contract.get_user_tasks(account.clone(), None, None, None);
+        assert_eq!(tasks.len(), 2);
+        assert_eq!(tasks[0].1.deadline_time, NOW + day);
+        assert_eq!(tasks[0].1.recurring_id, Some(recurring_id));
+        assert_eq!(record_id, tasks[1].0);
+        assert_eq!(tasks[1].1.deposit_status, DepositStatus::Refunded);
+        assert_eq!(received_contract.get_claimable(account.clone()).0, DEFAULT_MIN_DEPOSIT);
+
+        testing_env!(context
+            .predecessor_account_id(to_valid_account("keeper.testnet"))
+            .block_timestamp(NOW + 3 * day)
+            .build());
+        assert_eq!(received_contract.settle_recurring_task(account.clone(), recurring_id), 2);
+
+        let stats = received_contract.get_treasury_stats();
+        assert_eq!(stats.total_withheld.0, 2 * DEFAULT_MIN_DEPOSIT);
+        assert_eq!(stats.outstanding_deposits.0, 0);
+        let recurring_tasks = received_contract.get_recurring_tasks(account);
+        assert_eq!(recurring_tasks[0].1.record_ids.len(), 3);
+    }
+
+    #[test]
+    #[should_panic(expected = "Attached deposit must be")]
+    fn check_deposit_of_recurring_task() {
+        let mut context = get_context(to_valid_account("lrn.testnet"));
+        testing_env!(context.attached_deposit(DEFAULT_MIN_DEPOSIT).build());
+        let mut received_contract = Contract::new(to_valid_account(OWNER), Config::default());
+
+        received_contract.create_recurring_task(
+            "code review".to_string(),
+            Schedule::EveryNDays(2),
+            U128(DEFAULT_MIN_DEPOSIT),
+            5,
+        );
+    }
+
+    #[test]
+    fn check_user_stats() {
+        let mut context = get_context(to_valid_account("lrn.testnet"));
+        testing_env!(context.attached_deposit(DEFAULT_MIN_DEPOSIT).build());
+        let account = context.build().predecessor_account_id;
+        let mut received_contract = Contract::new(to_valid_account(OWNER), Config::default());
+
+        for _ in 0..4 {
+            received_contract.create_task("default task".to_string(), deadline(1658179621), None, None);
+        }
+        received_contract.make_complete_task_status(1);
+        received_contract.make_complete_task_status(2);
+
+        testing_env!(context.block_timestamp(AFTER_DEADLINE).build());
+        received_contract.make_complete_task_status(3);
+        received_contract.settle_expired_tasks(account.clone(), None, None);
+
+        let stats = received_contract.get_user_stats(account);
+        assert_eq!(stats.tasks_created, 4);
+        assert_eq!(stats.completed_on_time, 2);
+        assert_eq!(stats.completed_late, 1);
+        assert_eq!(stats.withheld, 2);
+        assert_eq!(stats.total_staked.0, 4 * DEFAULT_MIN_DEPOSIT);
+        assert_eq!(stats.total_refunded.0, 2 * DEFAULT_MIN_DEPOSIT);
+        assert_eq!(stats.total_forfeited.0, 2 * DEFAULT_MIN_DEPOSIT);
+        assert_eq!(stats.current_streak, 0);
+        assert_eq!(stats.longest_streak, 2);
+    }
+
+    #[test]
+    fn check_leaderboard() {
+        let mut context = get_context(to_valid_account("lrn.testnet"));
+        testing_env!(context.attached_deposit(DEFAULT_MIN_DEPOSIT).build());
+        let config = Config {
+            leaderboard_min_tasks: 2,
+            ..Config::default()
+        };
+        let mut received_contract = Contract::new(to_valid_account(OWNER), config);
+
+        received_contract.set_public_stats(true);
+        received_contract.create_task("default task".to_string(), deadline(1658179621), None, None);
+        received_contract.make_complete_task_status(1);
+        assert!(received_contract
+            .get_leaderboard(LeaderboardMetric::OnTimeRate, None)
+            .is_empty());
+
+        received_contract.create_task("default task".to_string(), deadline(1658179621), None, None);
+        received_contract.make_complete_task_status(2);
+
+        testing_env!(context
+            .predecessor_account_id(to_valid_account("alice.testnet"))
+            .attached_deposit(2 * DEFAULT_MIN_DEPOSIT)
+            .build());
+        received_contract.create_task("default task".to_string(), deadline(1658179621), None, None);
+        received_contract.set_public_stats(true);
+
+        let leaderboard = received_contract.get_leaderboard(LeaderboardMetric::TotalStaked, None);
+        let accounts: Vec<&str> = leaderboard.iter().map(|entry| entry.account_id.as_str()).collect();
+        assert_eq!(accounts, vec!["lrn.testnet", "alice.testnet"]);
+        assert_eq!(leaderboard[0].score.0, 2 * DEFAULT_MIN_DEPOSIT);
+        assert_eq!(received_contract.get_leaderboard(LeaderboardMetric::TotalStaked, Some(1)).len(), 1);
+
+        let leaderboard = received_contract.get_leaderboard(LeaderboardMetric::OnTimeRate, None);
+        assert_eq!(leaderboard.len(), 1);
+        assert_eq!(leaderboard[0].score.0, 10_000);
+
+        testing_env!(context.predecessor_account_id(to_valid_account("lrn.testnet")).build());
+        received_contract.set_public_stats(false);
+        let leaderboard = received_contract.get_leaderboard(LeaderboardMetric::TotalStaked, None);
+        assert_eq!(leaderboard.len(), 1);
+        assert_eq!(leaderboard[0].account_id.as_str(), "alice.testnet");
+        assert!(received_contract
+            .get_leaderboard(LeaderboardMetric::TotalRecovered, None)
+            .is_empty());
+    }
+
+    #[test]
+    fn check_challenge() {
+        let mut context = get_context(to_valid_account("lrn.testnet"));
+        testing_env!(context.build());
+        let mut received_contract = Contract::new(to_valid_account(OWNER), Config::default());
+        let challenge_id = received_contract.create_challenge(
+            "sprint task".to_string(),
+            deadline(1658179621),
+            U128(DEFAULT_MIN_DEPOSIT),
+            Some(to_valid_account("coach.testnet")),
+        );
+
+        for account in ["lrn.testnet", "alice.testnet", "bob.testnet", "carol.testnet"] {
+            testing_env!(context
+                .predecessor_account_id(to_valid_account(account))
+                .attached_deposit(DEFAULT_MIN_DEPOSIT)
+                .build());
+            received_contract.join_challenge(challenge_id);
+            if account != "bob.testnet" {
+                received_contract.submit_challenge_completion(challenge_id, "ipfs://proof".to_string());
+            }
+        }
+        assert_eq!(
+            received_contract
+                .get_challenges(None, None, Some(to_valid_account("alice.testnet")))
+                .len(),
+            1
+        );
+
+        testing_env!(context
+            .predecessor_account_id(to_valid_account("coach.testnet"))
+            .attached_deposit(0)
+            .build());
+        received_contract.approve_challenge_completion(challenge_id, to_valid_account("lrn.testnet"));
+        received_contract.approve_challenge_completion(challenge_id, to_valid_account("alice.testnet"));
+        received_contract.reject_challenge_completion(challenge_id, to_valid_account("carol.testnet"));
+
+        testing_env!(context.block_timestamp(AFTER_DEADLINE).build());
+        received_contract.settle_challenge(challenge_id);
+        assert!(received_contract.get_challenge(challenge_id).unwrap().is_settled);
+        assert_eq!(received_contract.get_claimable(to_valid_account("lrn.testnet")).0, 0);
+
+        let platform_fee = 2 * DEFAULT_MIN_DEPOSIT / 20;
+        let payout = DEFAULT_MIN_DEPOSIT + (2 * DEFAULT_MIN_DEPOSIT - platform_fee) / 2;
+        for account in ["lrn.testnet", "alice.testnet"] {
+            testing_env!(context.predecessor_account_id(to_valid_account(account)).build());
+            assert_eq!(received_contract.claim_challenge_payout(challenge_id).0, payout);
+            assert_eq!(received_contract.get_claimable(to_valid_account(account)).0, payout);
+        }
+        assert_eq!(received_contract.get_claimable(to_valid_account("bob.testnet")).0, 0);
+        assert_eq!(received_contract.get_claimable(to_valid_account("carol.testnet")).0, 0);
+
+        let treasury = received_contract.get_treasury_stats();
+        assert_eq!(treasury.total_withheld.0, platform_fee);
+        assert_eq!(treasury.outstanding_deposits.0, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Only participants with approved completion get a payout")]
+    fn check_challenge_payout_of_unconfirmed_participant() {
+        let mut context = get_context(to_valid_account("lrn.testnet"));
+        testing_env!(context.build());
+        let mut received_contract = Contract::new(to_valid_account(OWNER), Config::default());
+        let challenge_id = received_contract.create_challenge(
+            "sprint task".to_string(),
+            deadline(1658179621),
+            U128(DEFAULT_MIN_DEPOSIT),
+            None,
+        );
+
+        testing_env!(context
+            .predecessor_account_id(to_valid_account("alice.testnet"))
+            .attached_deposit(DEFAULT_MIN_DEPOSIT)
+            .build());
+        received_contract.join_challenge(challenge_id);
+        received_contract.submit_challenge_completion(challenge_id, "ipfs://proof".to_string());
+
+        testing_env!(context
+            .predecessor_account_id(to_valid_account("lrn.testnet"))
+            .attached_deposit(0)
+            .build());
+        received_contract.reject_challenge_completion(challenge_id, to_valid_account("alice.testnet"));
+
+        testing_env!(context
+            .predecessor_account_id(to_valid_account("alice.testnet"))
+            .block_timestamp(AFTER_DEADLINE)
+            .build());
+        received_contract.settle_challenge(challenge_id);
+        received_contract.claim_challenge_payout(challenge_id);
+    }
+
+    #[test]
+    #[should_panic(expected = "Verifier can still answer completion claims of the challenge")]
+    fn check_settlement_of_challenge_with_pending_claim() {
+        let mut context = get_context(to_valid_account("lrn.testnet"));
+        testing_env!(context.build());
+        let mut received_contract = Contract::new(to_valid_account(OWNER), Config::default());
+        let challenge_id = received_contract.create_challenge(
+            "sprint task".to_string(),
+            deadline(1658179621),
+            U128(DEFAULT_MIN_DEPOSIT),
+            None,
+        );
+
+        testing_env!(context
+            .predecessor_account_id(to_valid_account("alice.testnet"))
+            .attached_deposit(DEFAULT_MIN_DEPOSIT)
+            .build());
+        received_contract.join_challenge(challenge_id);
+        received_contract.submit_challenge_completion(challenge_id, "ipfs://proof".to_string());
+
+        testing_env!(context.block_timestamp(AFTER_DEADLINE).attached_deposit(0).build());
+        received_contract.settle_challenge(challenge_id);
+    }
+
+    #[test]
+    #[should_panic(expected = "Attached deposit must be")]
+    fn check_join_of_challenge_with_wrong_stake() {
+        let mut context = get_context(to_valid_account("lrn.testnet"));
+        testing_env!(context.build());
+        let mut received_contract = Contract::new(to_valid_account(OWNER), Config::default());
+        let challenge_id = received_contract.create_challenge(
+            "sprint task".to_string(),
+            deadline(1658179621),
+            U128(DEFAULT_MIN_DEPOSIT),
+            None,
+        );
+
+        testing_env!(context
+            .predecessor_account_id(to_valid_account("alice.testnet"))
+            .attached_deposit(DEFAULT_MIN_DEPOSIT - 1)
+            .build());
+        received_contract.join_challenge(challenge_id);
+    }
+
+    #[test]
+    #[should_panic(expected = "Verifier of the challenge can not join it")]
+    fn check_join_of_challenge_by_verifier() {
+        let mut context = get_context(to_valid_account("lrn.testnet"));
+        testing_env!(context.build());
+        let mut received_contract = Contract::new(to_valid_account(OWNER), Config::default());
+        let challenge_id = received_contract.create_challenge(
+            "sprint task".to_string(),
+            deadline(1658179621),
+            U128(DEFAULT_MIN_DEPOSIT),
+            Some(to_valid_account("coach.testnet")),
+        );
+
+        testing_env!(context
+            .predecessor_account_id(to_valid_account("coach.testnet"))
+            .attached_deposit(DEFAULT_MIN_DEPOSIT)
+            .build());
+        received_contract.join_challenge(challenge_id);
+    }
+
+    #[test]
+    fn check_pages_of_challenges() {
+        let mut context = get_context(to_valid_account("lrn.testnet"));
+        testing_env!(context.build());
+        let mut received_contract = Contract::new(to_valid_account(OWNER), Config::default());
+        for _ in 0..5 {
+            let challenge_id = received_contract.create_challenge(
+                "sprint task".to_string(),
+                deadline(1658179621),
+                U128(DEFAULT_MIN_DEPOSIT),
+                Some(to_valid_account("coach.testnet")),
+            );
+            if challenge_id % 2 == 0 {
+                testing_env!(context.attached_deposit(DEFAULT_MIN_DEPOSIT).build());
+                received_contract.join_challenge(challenge_id);
+                testing_env!(context.attached_deposit(0).build());
+            }
+        }
+
+        let challenge_ids = |challenges: Vec<(i64, Challenge)>| -> Vec<i64> {
+            challenges.into_iter().map(|(challenge_id, _)| challenge_id).collect()
+        };
+        assert_eq!(challenge_ids(received_contract.get_challenges(None, None, None)), vec![1, 2, 3, 4, 5]);
+        assert_eq!(challenge_ids(received_contract.get_challenges(Some(2), Some(2), None)), vec![2, 3]);
+        let participant = Some(to_valid_account("lrn.testnet"));
+        assert_eq!(challenge_ids(received_contract.get_challenges(Some(3), Some(10), participant)), vec![4]);
+    }
+
+    fn milestone(id: &str, threshold: u128, soulbound: bool) -> Milestone {
+        Milestone {
+            id: id.to_string(),
+            title: "Achievement".to_string(),
+            description: "Completed tasks on time".to_string(),
+            kind: MilestoneKind::CompletedOnTime,
+            threshold: U128(threshold),
+            soulbound,
+        }
+    }
+
+    #[test]
+    fn check_achievements() {
+        let mut context = get_context(to_valid_account(OWNER));
+        testing_env!(context.build());
+        let account = to_valid_account("lrn.testnet");
+        let mut received_contract = Contract::new(to_valid_account(OWNER), Config::default());
+        received_contract.set_milestones(vec![milestone("first", 1, true), milestone("second", 2, false)]);
+
+        testing_env!(context
+            .predecessor_account_id(account.clone())
+            .attached_deposit(DEFAULT_MIN_DEPOSIT)
+            .build());
+        received_contract.create_task("default task".to_string(), deadline(1658179621), None, None);
+        received_contract.make_complete_task_status(1);
+
+        let tokens = received_contract.nft_tokens_for_owner(account.clone(), None, None);
+        assert_eq!(tokens.len(), 1);
+        assert_eq!(tokens[0].token_id, "first:lrn.testnet");
+        let metadata = tokens[0].metadata.clone().unwrap();
+        assert_eq!(metadata.media.unwrap(), "nft/first:lrn.testnet.svg");
+        assert!(metadata.media_hash.is_some());
+
+        // Later changes of the stats do not mint the same achievement again
+        received_contract.create_task("default task".to_string(), deadline(1658179621), None, None);
+        received_contract.make_complete_task_status(2);
+        assert_eq!(received_contract.nft_supply_for_owner(account).0, 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "Achievement is soulbound and can not be transferred")]
+    fn check_transfer_of_soulbound_achievement() {
+        let mut context = get_context(to_valid_account(OWNER));
+        testing_env!(context.build());
+        let mut received_contract = Contract::new(to_valid_account(OWNER), Config::default());
+        received_contract.set_milestones(vec![milestone("first", 1, true)]);
+
+        testing_env!(context
+            .predecessor_account_id(to_valid_account("lrn.testnet"))
+            .attached_deposit(DEFAULT_MIN_DEPOSIT)
+            .build());
+        received_contract.create_task("default task".to_string(), deadline(1658179621), None, None);
+        received_contract.make_complete_task_status(1);
+
+        testing_env!(context.attached_deposit(1).build());
+        received_contract.nft_transfer(
+            to_valid_account("alice.testnet"),
+            "first:lrn.testnet".to_string(),
+            None,
+            None,
+        );
+    }
+
+    fn web4_response(contract: &Contract, request: near_sdk::serde_json::Value) -> (Option<u64>, String) {
+        let request: web4::Web4Request = near_sdk::serde_json::from_value(request).unwrap();
+        let response = near_sdk::serde_json::to_value(contract.web4_get(request)).unwrap();
+        let body: near_sdk::json_types::Base64VecU8 =
+            near_sdk::serde_json::from_value(response["body"].clone()).unwrap();
+        (response["status"].as_u64(), String::from_utf8(body.into()).unwrap())
+    }
+
+    fn web4_page(contract: &Contract, request: near_sdk::serde_json::Value) -> String {
+        let (status, body) = web4_response(contract, request);
+        assert_eq!(status, None);
+        body
+    }
+
+    #[test]
+    fn check_web4_dashboards() {
+        let mut context = get_context(to_valid_account("lrn.testnet"));
+        testing_env!(context.attached_deposit(DEFAULT_MIN_DEPOSIT).build());
+        let mut received_contract = Contract::new(to_valid_account(OWNER), Config::default());
+        received_contract.create_task("<b>default task</b>".to_string(), deadline(1658179621), None, None);
+
+        let page = web4_page(&received_contract, near_sdk::serde_json::json!({"path": "/"}));
+        assert!(page.contains("Sign in with your NEAR account"));
+
+        let page = web4_page(
+            &received_contract,
+            near_sdk::serde_json::json!({"accountId": "alice.testnet", "path": "/"}),
+        );
+        assert!(page.contains("Tasks of alice.testnet"));
+        assert!(page.contains("No tasks yet"));
+
+        let page = web4_page(&received_contract, near_sdk::serde_json::json!({"path": "/u/lrn.testnet"}));
+        assert!(page.contains("Tasks of lrn.testnet"));
+        assert!(page.contains("&lt;b&gt;default task&lt;/b&gt;"));
+    }
+
+    #[test]
+    fn check_web4_router() {
+        let mut context = get_context(to_valid_account("lrn.testnet"));
+        testing_env!(context.attached_deposit(DEFAULT_MIN_DEPOSIT).build());
+        let mut received_contract = Contract::new(to_valid_account(OWNER), Config::default());
+        received_contract.create_task("first task".to_string(), deadline(1658179621), None, None);
+        received_contract.create_task("second task".to_string(), deadline(1658179621), None, None);
+        received_contract.make_complete_task_status(2);
+
+        let page = web4_page(
+            &received_contract,
+            near_sdk::serde_json::json!({"path": "/u/lrn.testnet/tasks", "query": {"status": ["completed"]}}),
+        );
+        assert!(page.contains("second task") && !page.contains("first task"));
+
+        let page = web4_page(
+            &received_contract,
+            near_sdk::serde_json::json!({"path": "/u/lrn.testnet/tasks?status=open&page=2"}),
+        );
+        assert!(page.contains("No tasks found"));
+
+        let page = web4_page(
+            &received_contract,
+            near_sdk::serde_json::json!({"accountId": "lrn.testnet", "path": "/task/1"}),
+        );
+        assert!(page.contains("first task"));
+
+        for path in ["/", "/add-task", "/complete", "/leaderboard", "/u/lrn.testnet/task/1"] {
+            let page = web4_page(&received_contract, near_sdk::serde_json::json!({ "path": path }));
+            assert!(!page.contains("STYLESHEET") && !page.contains("{{"));
+        }
+
+        for path in ["/unknown", "/task/1", "/u/lrn.testnet/task/3", "/u/lrn.testnet/tasks?status=late"] {
+            let (status, page) = web4_response(&received_contract, near_sdk::serde_json::json!({ "path": path }));
+            assert_eq!(status, Some(404));
+            assert!(page.contains("Page not found"));
+        }
+    }
+
+    #[test]
+    fn check_web4_api() {
+        let mut context = get_context(to_valid_account("lrn.testnet"));
+        testing_env!(context.attached_deposit(DEFAULT_MIN_DEPOSIT).build());
+        let mut received_contract = Contract::new(to_valid_account(OWNER), Config::default());
+        for _ in 0..3 {
+            received_contract.create_task("default task".to_string(), deadline(1658179621), None, None);
+        }
+        received_contract.make_complete_task_status(1);
+
+        let api = |path: &str| {
+            let (status, body) = web4_response(&received_contract, near_sdk::serde_json::json!({ "path": path }));
+            (status, near_sdk::serde_json::from_str::<near_sdk::serde_json::Value>(&body).unwrap())
+        };
+
+        let (_, page) = api("/api/users/lrn.testnet/tasks.json?status=open&limit=1&page=2");
+        assert_eq!(page["total"], 2);
+        assert_eq!(page["tasks"].as_array().unwrap().len(), 1);
+        assert_eq!(page["tasks"][0]["record_id"], 3);
+        assert_eq!(page["tasks"][0]["deposit"], DEFAULT_MIN_DEPOSIT.to_string());
+
+        let (_, task) = api("/api/tasks/lrn.testnet/1.json");
+        assert_eq!(task["is_complete"], true);
+        assert_eq!(task["deposit_status"], "Refunded");
+
+        let (_, stats) = api("/api/stats.json");
+        assert_eq!(stats["treasury"]["total_contributed"], (3 * DEFAULT_MIN_DEPOSIT).to_string());
+
+        let (_, stats) = api("/api/users/lrn.testnet/stats.json");
+        assert_eq!(stats["tasks_created"], 3);
+
+        for path in ["/api/tasks/lrn.testnet/9.json", "/api/unknown"] {
+            let (status, error) = api(path);
+            assert_eq!(status, Some(404));
+            assert_eq!(error["error"], "Not found");
+        }
+
+        for (query, name) in [("limit=1000", "limit"), ("page=0", "page"), ("page=x", "page"), ("status=late", "status")] {
+            let (status, error) = api(&format!("/api/users/lrn.testnet/tasks.json?{}", query));
+            assert_eq!(status, Some(400));
+            assert_eq!(error["error"], format!("Invalid parameter `{}`", name));
+        }
+    }
+
+    #[test]
+    fn check_calendar_feed() {
+        assert_eq!(
+            crate::deadline::format_utc_basic(1_709_164_800_000_000_000),
+            "20240229T000000Z"
+        );
+
+        let mut context = get_context(to_valid_account("lrn.testnet"));
+        testing_env!(context.attached_deposit(DEFAULT_MIN_DEPOSIT).build());
+        let mut received_contract = Contract::new(to_valid_account(OWNER), Config::default());
+        received_contract.create_task("Write a post, then rest".to_string(), deadline(1658179621), None, None);
+        received_contract.create_task("done task".to_string(), deadline(1658179621), None, None);
+        received_contract.make_complete_task_status(2);
+        for _ in 1..crate::calendar::MAX_CALENDAR_TASKS {
+            // Keeps the logs of the events below the limit of a transaction
+            testing_env!(context.build());
+            received_contract.create_task("default task".to_string(), deadline(1658179621), None, None);
+        }
+        received_contract.create_task("cancelled task".to_string(), deadline(1658179621), None, None);
+        received_contract.cancel_task(crate::calendar::MAX_CALENDAR_TASKS as i64 + 2);
+
+        let feed = web4_page(&received_contract, near_sdk::serde_json::json!({"path": "/u/lrn.testnet/calendar.ics"}));
+        assert!(feed.starts_with("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n"));
+        assert!(feed.ends_with("END:VCALENDAR\r\n"));
+        assert_eq!(feed.matches("BEGIN:VTODO").count(), crate::calendar::MAX_CALENDAR_TASKS);
+        assert!(feed.contains("\r\nDUE:20220718T212701Z\r\n"));
+        assert!(feed.contains("\r\nDESCRIPTION:Stake: 3 NEAR\\nDeposit: Contributed\r\n"));
+        // Only the latest tasks are shown
+        assert!(feed.contains("\r\nSUMMARY:cancelled task\r\n"));
+        assert!(feed.contains("\r\nSTATUS:CANCELLED\r\n"));
+        assert!(!feed.contains("SUMMARY:Write a post"));
+        assert!(!feed.contains("SUMMARY:done task"));
+
+        // Tasks that were completed and refunded are left out
+        testing_env!(context.predecessor_account_id(to_valid_account("alice.testnet")).build());
+        received_contract.create_task("Write a post, then rest".to_string(), deadline(1658179621), None, None);
+        received_contract.create_task("done task".to_string(), deadline(1658179621), None, None);
+        received_contract.make_complete_task_status(2);
+
+        let feed = web4_page(&received_contract, near_sdk::serde_json::json!({"path": "/u/alice.testnet/calendar.ics"}));
+        assert_eq!(feed.matches("BEGIN:VTODO").count(), 1);
+        assert!(feed.contains("\r\nSUMMARY:Write a post\\, then rest\r\n"));
+        assert!(feed.contains("\r\nSTATUS:NEEDS-ACTION\r\n"));
     }
 }
@@ -0,0 +1,84 @@
+use crate::*;
+
+use crate::events::{Event, ProofSubmittedData};
+
+/// Maximal number of proofs attached to a task
+const MAX_PROOFS_PER_TASK: usize = 10;
+
+/// Evidence of completion attached to a task
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Proof {
+    /// Where the evidence can be found, e.g. a link to a commit or an IPFS URI
+    pub uri: String,
+    /// Hash of the evidence, e.g. a commit hash, an IPFS CID or a SHA-256 of a photo
+    pub hash: String,
+    /// Time of submission, in nanoseconds
+    pub submitted_at: Timestamp,
+}
+
+impl Record {
+    /// Checks whether a proof was submitted before the deadline
+    pub fn has_timely_proof(&self) -> bool {
+        self.proofs
+            .iter()
+            .any(|proof| proof.submitted_at < self.deadline_time)
+    }
+
+    /// Checks whether completion of the task still counts as done in time: the deadline
+    /// has not passed or, for a task with a verifier, a proof was submitted before it.
+    /// Nobody checks proofs of tasks without a verifier, so they do not extend the deadline
+    pub fn is_on_time(&self) -> bool {
+        self.deadline_time > env::block_timestamp()
+            || (self.verifier.is_some() && self.has_timely_proof())
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Attaches evidence of completion to a task before its deadline. A task with a verifier
+    /// and a proof is not settled as expired and can be confirmed by the verifier
+    /// after the deadline without losing the deposit
+    pub fn submit_proof(&mut self, record_id: i64, proof_uri: String, proof_hash: String) {
+        let account_id = env::predecessor_account_id();
+        self.assert_migrated(&account_id);
+        let mut user_records = self.get_user_records(&account_id).expect("User not found");
+        let mut record = user_records.get_record(record_id).expect("Task not found");
+
+        assert!(
+            !record.is_complete_status && record.deposit_status == DepositStatus::Contributed,
+            "Proofs can be submitted only for tasks in progress"
+        );
+        assert!(
+            record.deadline_time > env::block_timestamp(),
+            "Deadline was ended, proof can not be submitted"
+        );
+        assert!(
+            record.proofs.len() < MAX_PROOFS_PER_TASK,
+            "Task can not have more than {} proofs",
+            MAX_PROOFS_PER_TASK
+        );
+        assert!(!proof_hash.is_empty(), "Proof hash can not be empty");
+        assert!(
+            proof_uri.len() + proof_hash.len() <= self.config.max_task_length as usize,
+            "Proof is longer than {} bytes",
+            self.config.max_task_length
+        );
+
+        record.proofs.push(Proof {
+            uri: proof_uri.clone(),
+            hash: proof_hash.clone(),
+            submitted_at: env::block_timestamp(),
+        });
+        user_records.set_record(record_id, &record);
+        self.set_user_records(&account_id, user_records);
+
+        Event::ProofSubmitted(vec![ProofSubmittedData {
+            account_id,
+            record_id,
+            proof_uri,
+            proof_hash,
+        }])
+        .emit();
+    }
+}
@@ -0,0 +1,272 @@
+use crate::*;
+
+use crate::config::ONE_DAY;
+use crate::events::{Event, RecurringTaskCreatedData, TaskCompletedData, TaskCreatedData};
+
+/// Maximal number of occurrences of a recurring task
+const MAX_OCCURRENCES: u32 = 366;
+
+/// How often a recurring task has to be done
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum Schedule {
+    Daily,
+    Weekly,
+    EveryNDays(u32),
+}
+
+impl Schedule {
+    /// Length of one period, in nanoseconds
+    pub fn period(&self) -> u64 {
+        match self {
+            Schedule::Daily => ONE_DAY,
+            Schedule::Weekly => 7 * ONE_DAY,
+            Schedule::EveryNDays(days) => *days as u64 * ONE_DAY,
+        }
+    }
+}
+
+/// A habit that has to be done once in every period of the schedule. The deposits of all
+/// occurrences are paid up front, a `Record` of an occurrence is created when it is completed
+/// or settled, its deadline is the end of the period
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RecurringTask {
+    /// Description of the habit
+    pub task: String,
+    pub schedule: Schedule,
+    /// Deposit of every occurrence, in yoctoNear
+    pub deposit_per_occurrence: Balance,
+    /// Number of periods
+    pub occurrences: u32,
+    /// Start of the first period, in nanoseconds
+    pub starts_at: Timestamp,
+    /// Ids of records of occurrences that were created, in the order of periods
+    pub record_ids: Vec<i64>,
+}
+
+impl RecurringTask {
+    /// Deadline of the occurrence, the end of its period
+    pub fn deadline_of(&self, occurrence: u32) -> Timestamp {
+        self.starts_at + (occurrence as u64 + 1) * self.schedule.period()
+    }
+
+    /// Number of periods that have ended by the time
+    fn ended_periods(&self, now: Timestamp) -> u32 {
+        let ended = now.saturating_sub(self.starts_at) / self.schedule.period();
+        std::cmp::min(ended, self.occurrences as u64) as u32
+    }
+}
+
+impl Contract {
+    /// Creates records of occurrences up to the given one, records of earlier periods
+    /// that were not completed are left to settlement
+    fn materialize_occurrences(
+        &mut self,
+        account_id: &AccountId,
+        user_records: &mut UserRecords,
+        recurring_id: i64,
+        recurring_task: &mut RecurringTask,
+        up_to: u32,
+    ) {
+        let mut created = Vec::new();
+        while (recurring_task.record_ids.len() as u32) < up_to {
+            let occurrence = recurring_task.record_ids.len() as u32;
+            let record = Record {
+                task: recurring_task.task.clone(),
+                is_complete_status: false,
+                guarantee_of_task_completion: recurring_task.deposit_per_occurrence,
+                deadline_time: recurring_task.deadline_of(occurrence),
+                account_balance: env::account_balance(),
+                deposit_status: DepositStatus::Contributed,
+                created_at: recurring_task.deadline_of(occurrence) - recurring_task.schedule.period(),
+                history: Vec::new(),
+                token_id: None,
+                verifier: None,
+                beneficiary: None,
+                completion_claim: None,
+                proofs: Vec::new(),
+                recurring_id: Some(recurring_id),
+            };
+            let record_id = user_records.record_id;
+            user_records.set_record(record_id, &record);
+            user_records.record_id += 1;
+            user_records.stats.add_created(None, record.guarantee_of_task_completion);
+            recurring_task.record_ids.push(record_id);
+
+            created.push(TaskCreatedData {
+                account_id: account_id.clone(),
+                record_id,
+                deadline_time: U64(record.deadline_time),
+                deposit: U128(record.guarantee_of_task_completion),
+                token_id: None,
+            });
+        }
+        user_records
+            .recurring_tasks
+            .insert(&recurring_id, recurring_task);
+
+        if !created.is_empty() {
+            Event::TaskCreated(created).emit();
+        }
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Creates a habit that has to be done once in every period of the schedule,
+    /// the first period starts now. The deposits of all occurrences are paid up front,
+    /// every occurrence is refunded or withheld on its own. Returns the id of the recurring task
+    #[payable]
+    pub fn create_recurring_task(
+        &mut self,
+        task: String,
+        schedule: Schedule,
+        deposit_per_occurrence: U128,
+        occurrences: u32,
+    ) -> i64 {
+        assert!(!self.paused, "Creation of tasks is paused");
+        assert!(
+            deposit_per_occurrence.0 >= self.config.min_deposit.0,
+            "For creation task you need pay minimum {} yoctoNEAR",
+            self.config.min_deposit.0
+        );
+        assert!(
+            deposit_per_occurrence.0 <= self.config.max_deposit.0,
+            "For creation task you can pay maximum {} yoctoNEAR",
+            self.config.max_deposit.0
+        );
+        assert!(
+            task.len() <= self.config.max_task_length as usize,
+            "Task description is longer than {} bytes",
+            self.config.max_task_length
+        );
+        assert!(
+            occurrences > 0 && occurrences <= MAX_OCCURRENCES,
+            "Number of occurrences must be from 1 to {}",
+            MAX_OCCURRENCES
+        );
+        assert!(
+            schedule != Schedule::EveryNDays(0),
+            "Period of the schedule can not be empty"
+        );
+        assert!(
+            occurrences as u64 * schedule.period() <= self.config.max_deadline_horizon.0,
+            "Deadline is too far in the future"
+        );
+        let total_deposit = deposit_per_occurrence.0 * occurrences as Balance;
+        assert_eq!(
+            env::attached_deposit(),
+            total_deposit,
+            "Attached deposit must be {} yoctoNEAR",
+            total_deposit
+        );
+
+        let account_id = env::predecessor_account_id();
+        self.assert_migrated(&account_id);
+        let mut user_records = self
+            .get_user_records(&account_id)
+            .unwrap_or_else(|| UserRecords::new(&account_id));
+
+        let recurring_task = RecurringTask {
+            task,
+            schedule,
+            deposit_per_occurrence: deposit_per_occurrence.0,
+            occurrences,
+            starts_at: env::block_timestamp(),
+            record_ids: Vec::new(),
+        };
+        let recurring_id = user_records.recurring_id;
+        user_records
+            .recurring_tasks
+            .insert(&recurring_id, &recurring_task);
+        user_records.recurring_id += 1;
+        self.set_user_records(&account_id, user_records);
+        self.treasury.total_contributed += total_deposit;
+
+        Event::RecurringTaskCreated(vec![RecurringTaskCreatedData {
+            account_id,
+            recurring_id,
+            schedule,
+            occurrences,
+            deposit: U128(total_deposit),
+        }])
+        .emit();
+
+        recurring_id
+    }
+
+    /// Returns recurring tasks of the user
+    pub fn get_recurring_tasks(&self, account_id: AccountId) -> Vec<(i64, RecurringTask)> {
+        self.get_user_records(&account_id)
+            .map(|user_records| user_records.recurring_tasks.to_vec())
+            .unwrap_or_default()
+    }
+
+    /// Completes the occurrence of the current period, its deposit is added to the claimable
+    /// refunds of the user. Returns the id of the record of the occurrence
+    pub fn complete_recurring_task(&mut self, recurring_id: i64) -> i64 {
+        let account_id = env::predecessor_account_id();
+        self.assert_migrated(&account_id);
+        let mut user_records = self.get_user_records(&account_id).expect("User not found");
+        let mut recurring_task = user_records
+            .recurring_tasks
+            .get(&recurring_id)
+            .expect("Recurring task not found");
+
+        let occurrence = recurring_task.ended_periods(env::block_timestamp());
+        assert!(
+            occurrence < recurring_task.occurrences,
+            "All periods of the recurring task have ended"
+        );
+        self.materialize_occurrences(
+            &account_id,
+            &mut user_records,
+            recurring_id,
+            &mut recurring_task,
+            occurrence + 1,
+        );
+
+        let record_id = recurring_task.record_ids[occurrence as usize];
+        let mut record = user_records.get_record(record_id).unwrap();
+        assert!(!record.is_complete_status, "Task already completed");
+        record.is_complete_status = true;
+
+        Event::TaskCompleted(vec![TaskCompletedData {
+            account_id: account_id.clone(),
+            record_id,
+            on_time: true,
+        }])
+        .emit();
+
+        self.refund_deposit(&account_id, record_id, &mut record, &mut user_records);
+        user_records.set_record(record_id, &record);
+        self.set_user_records(&account_id, user_records);
+        record_id
+    }
+
+    /// Withholds deposits of the occurrences whose periods have ended without completion.
+    /// Anyone can call the method, the caller receives the keeper fee from every withheld deposit.
+    /// Returns the number of settled occurrences
+    pub fn settle_recurring_task(&mut self, account_id: AccountId, recurring_id: i64) -> u32 {
+        self.assert_migrated(&account_id);
+        let mut user_records = self.get_user_records(&account_id).expect("User not found");
+        let mut recurring_task = user_records
+            .recurring_tasks
+            .get(&recurring_id)
+            .expect("Recurring task not found");
+
+        let ended_periods = recurring_task.ended_periods(env::block_timestamp());
+        self.materialize_occurrences(
+            &account_id,
+            &mut user_records,
+            recurring_id,
+            &mut recurring_task,
+            ended_periods,
+        );
+        let record_ids = recurring_task.record_ids[..ended_periods as usize].to_vec();
+        let settled = self.settle_records(&account_id, &mut user_records, record_ids);
+        self.set_user_records(&account_id, user_records);
+        settled
+    }
+}
@@ -0,0 +1,52 @@
+use crate::*;
+
+use near_sdk::{is_promise_success, Gas};
+
+const GAS_FOR_CLAIM_CALLBACK: Gas = Gas(10_000_000_000_000);
+
+#[near_bindgen]
+impl Contract {
+    /// Returns refunded deposits that the user can claim, in yoctoNear
+    pub fn get_claimable(&self, account_id: AccountId) -> U128 {
+        U128(
+            self.get_user_records(&account_id)
+                .map(|user_records| user_records.claimable_refunds)
+                .unwrap_or(0),
+        )
+    }
+
+    /// Sends all refunded deposits of the user in one transfer,
+    /// to the beneficiary if it is given or to the user otherwise
+    pub fn claim_refunds(&mut self, beneficiary: Option<AccountId>) -> Promise {
+        let account_id = env::predecessor_account_id();
+        self.assert_migrated(&account_id);
+        let mut user_records = self.get_user_records(&account_id).expect("User not found");
+        let amount = user_records.claimable_refunds;
+        assert!(amount > 0, "Nothing to claim");
+
+        user_records.claimable_refunds = 0;
+        self.set_user_records(&account_id, user_records);
+        self.treasury.total_claimed += amount;
+
+        Promise::new(beneficiary.unwrap_or_else(|| account_id.clone()))
+            .transfer(amount)
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_CLAIM_CALLBACK)
+                    .on_claim_refunds_complete(account_id, U128(amount)),
+            )
+    }
+
+    /// Restores the claimable refunds if the transfer failed
+    #[private]
+    pub fn on_claim_refunds_complete(&mut self, account_id: AccountId, amount: U128) -> bool {
+        if !is_promise_success() {
+            let mut user_records = self.get_user_records(&account_id).expect("User not found");
+            user_records.claimable_refunds += amount.0;
+            self.set_user_records(&account_id, user_records);
+            self.treasury.total_claimed -= amount.0;
+            return false;
+        }
+        true
+    }
+}
@@ -0,0 +1,130 @@
+use crate::*;
+
+use crate::beneficiary::{Donation, MAX_DONATIONS_PER_CALL};
+use crate::events::{Event, TaskSettledData};
+use crate::utils::bps_share;
+use near_sdk::json_types::U128;
+use near_sdk::{is_promise_success, Gas};
+
+const GAS_FOR_KEEPER_FEE_CALLBACK: Gas = Gas(10_000_000_000_000);
+/// Every record of a default batch can be donated to its own beneficiary within one call
+const DEFAULT_SETTLEMENT_LIMIT: u64 = MAX_DONATIONS_PER_CALL as u64;
+
+impl Contract {
+    /// Withholds deposits of the given records that are overdue and pays the keeper fee
+    /// to the caller unless the caller is the user, returns the number of settled records. Donations are sent with one
+    /// transfer per beneficiary and token, records that would need more than
+    /// `MAX_DONATIONS_PER_CALL` transfers are left for the next call
+    pub(crate) fn settle_records(
+        &mut self,
+        account_id: &AccountId,
+        user_records: &mut UserRecords,
+        record_ids: Vec<i64>,
+    ) -> u32 {
+        let keeper_id = env::predecessor_account_id();
+        let mut settled = Vec::new();
+        let mut keeper_fee = 0;
+        let mut donations: Vec<Donation> = Vec::new();
+        for record_id in record_ids {
+            let mut record = match user_records.get_record(record_id) {
+                Some(record) if record.is_overdue() => record,
+                _ => continue,
+            };
+            if let Some(beneficiary_id) = record.beneficiary.clone() {
+                let donation = Donation {
+                    beneficiary_id,
+                    token_id: record.token_id.clone(),
+                    amount: 0,
+                };
+                if donations.len() == MAX_DONATIONS_PER_CALL
+                    && !donations.iter().any(|other| other.is_paid_with(&donation))
+                {
+                    continue;
+                }
+            }
+
+            // Keeper fees are paid in NEAR, deposits in tokens are withheld as a whole.
+            // Users settling their own tasks get no fee back from the deposit
+            let record_keeper_fee = match record.token_id {
+                None if &keeper_id != account_id => {
+                    bps_share(record.guarantee_of_task_completion, self.config.keeper_fee_bps)
+                }
+                _ => 0,
+            };
+            let donation =
+                self.withhold_deposit(account_id, record_id, &mut record, user_records, record_keeper_fee);
+            user_records.set_record(record_id, &record);
+            if let Some(donation) = donation {
+                match donations.iter_mut().find(|other| other.is_paid_with(&donation)) {
+                    Some(other) => other.amount += donation.amount,
+                    None => donations.push(donation),
+                }
+            }
+            keeper_fee += record_keeper_fee;
+            settled.push(TaskSettledData {
+                account_id: account_id.clone(),
+                record_id,
+                keeper_id: keeper_id.clone(),
+                keeper_fee: U128(record_keeper_fee),
+            });
+        }
+
+        if settled.is_empty() {
+            return 0;
+        }
+        let settled_count = settled.len() as u32;
+        Event::TaskSettled(settled).emit();
+        self.send_donations(donations);
+
+        if keeper_fee > 0 {
+            self.treasury.total_keeper_fees += keeper_fee;
+            Promise::new(keeper_id)
+                .transfer(keeper_fee)
+                .then(
+                    Self::ext(env::current_account_id())
+                        .with_static_gas(GAS_FOR_KEEPER_FEE_CALLBACK)
+                        .on_keeper_fee_complete(U128(keeper_fee)),
+                );
+        }
+        settled_count
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Withholds deposits of the user's tasks whose deadline has passed without completion.
+    /// Anyone can call the method, the caller receives the keeper fee from every withheld deposit.
+    /// Records are walked by ids in batches of `limit` starting from the id `from_index`, 1 by default,
+    /// returns the number of settled tasks
+    pub fn settle_expired_tasks(
+        &mut self,
+        account_id: AccountId,
+        from_index: Option<u64>,
+        limit: Option<u64>,
+    ) -> u32 {
+        self.assert_migrated(&account_id);
+        let mut user_records = match self.get_user_records(&account_id) {
+            Some(user_records) => user_records,
+            None => return 0,
+        };
+
+        let from_id = from_index.unwrap_or(1) as i64;
+        let limit = limit.unwrap_or(DEFAULT_SETTLEMENT_LIMIT) as i64;
+        let record_ids: Vec<i64> =
+            (from_id..std::cmp::min(user_records.record_id, from_id.saturating_add(limit))).collect();
+
+        let settled = self.settle_records(&account_id, &mut user_records, record_ids);
+        self.set_user_records(&account_id, user_records);
+        settled
+    }
+
+    /// Returns the keeper fee to the treasury if the transfer failed
+    #[private]
+    pub fn on_keeper_fee_complete(&mut self, amount: U128) -> bool {
+        if !is_promise_success() {
+            self.treasury.total_keeper_fees -= amount.0;
+            return false;
+        }
+        true
+    }
+}
@@ -0,0 +1,91 @@
+use crate::*;
+
+/// Running counters of the user's tasks, amounts are in yoctoNear,
+/// deposits paid in fungible tokens are counted only in the numbers of tasks
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct UserStats {
+    pub tasks_created: u64,
+    pub completed_on_time: u64,
+    pub completed_late: u64,
+    /// Tasks whose deposit was withheld
+    pub withheld: u64,
+    /// Deposits paid for creation of tasks
+    pub total_staked: U128,
+    /// Deposits returned to the user
+    pub total_refunded: U128,
+    /// Deposits and fees that stayed in the service or went to beneficiaries
+    pub total_forfeited: U128,
+    /// Tasks completed on time in a row
+    pub current_streak: u32,
+    pub longest_streak: u32,
+}
+
+impl Default for UserStats {
+    fn default() -> Self {
+        Self {
+            tasks_created: 0,
+            completed_on_time: 0,
+            completed_late: 0,
+            withheld: 0,
+            total_staked: U128(0),
+            total_refunded: U128(0),
+            total_forfeited: U128(0),
+            current_streak: 0,
+            longest_streak: 0,
+        }
+    }
+}
+
+impl UserStats {
+    fn near_amount(token_id: Option<&AccountId>, amount: Balance) -> Balance {
+        match token_id {
+            None => amount,
+            Some(_) => 0,
+        }
+    }
+
+    pub(crate) fn add_created(&mut self, token_id: Option<&AccountId>, deposit: Balance) {
+        self.tasks_created += 1;
+        self.add_staked(token_id, deposit);
+    }
+
+    /// Takes into account a deposit added to a task, e.g. the extra deposit of an extension
+    pub(crate) fn add_staked(&mut self, token_id: Option<&AccountId>, deposit: Balance) {
+        self.total_staked.0 += Self::near_amount(token_id, deposit);
+    }
+
+    pub(crate) fn add_completed_on_time(&mut self, token_id: Option<&AccountId>, refund: Balance) {
+        self.completed_on_time += 1;
+        self.total_refunded.0 += Self::near_amount(token_id, refund);
+        self.current_streak += 1;
+        self.longest_streak = std::cmp::max(self.longest_streak, self.current_streak);
+    }
+
+    pub(crate) fn add_completed_late(&mut self) {
+        self.completed_late += 1;
+        self.current_streak = 0;
+    }
+
+    pub(crate) fn add_withheld(&mut self, token_id: Option<&AccountId>, deposit: Balance) {
+        self.withheld += 1;
+        self.total_forfeited.0 += Self::near_amount(token_id, deposit);
+        self.current_streak = 0;
+    }
+
+    /// Takes into account a penalty or a fee paid from a deposit and the returned rest of it
+    pub(crate) fn add_fee(&mut self, token_id: Option<&AccountId>, fee: Balance, refund: Balance) {
+        self.total_forfeited.0 += Self::near_amount(token_id, fee);
+        self.total_refunded.0 += Self::near_amount(token_id, refund);
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Returns counters of the user's tasks, all counters are zero for unknown users
+    pub fn get_user_stats(&self, account_id: AccountId) -> UserStats {
+        self.get_user_records(&account_id)
+            .map(|user_records| user_records.stats)
+            .unwrap_or_default()
+    }
+}
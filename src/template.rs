@@ -0,0 +1,269 @@
+use std::collections::HashMap;
+
+/// Value of a template variable
+pub(crate) enum Value {
+    /// Text that is escaped when it is put into a page
+    Text(String),
+    /// Markup written by the contract itself that is put into a page as it is
+    Html(String),
+    Bool(bool),
+    /// Scopes of iterations of an `each` block
+    List(Vec<Scope>),
+}
+
+impl Value {
+    fn is_truthy(&self) -> bool {
+        match self {
+            Value::Text(text) | Value::Html(text) => !text.is_empty(),
+            Value::Bool(value) => *value,
+            Value::List(items) => !items.is_empty(),
+        }
+    }
+}
+
+/// Variables available to a template, set by a builder like
+/// `Scope::new().text("task", &record.task).flag("is_complete", true)`
+#[derive(Default)]
+pub(crate) struct Scope(HashMap<&'static str, Value>);
+
+impl Scope {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    pub(crate) fn text<T: ToString>(mut self, name: &'static str, value: T) -> Self {
+        self.0.insert(name, Value::Text(value.to_string()));
+        self
+    }
+
+    pub(crate) fn html<T: ToString>(mut self, name: &'static str, value: T) -> Self {
+        self.0.insert(name, Value::Html(value.to_string()));
+        self
+    }
+
+    pub(crate) fn flag(mut self, name: &'static str, value: bool) -> Self {
+        self.0.insert(name, Value::Bool(value));
+        self
+    }
+
+    pub(crate) fn list(mut self, name: &'static str, items: Vec<Scope>) -> Self {
+        self.0.insert(name, Value::List(items));
+        self
+    }
+}
+
+/// Part of a parsed template
+enum Node<'a> {
+    Text(&'a str),
+    Variable { name: &'a str, escape: bool },
+    Each { name: &'a str, body: Vec<Node<'a>> },
+    If { name: &'a str, then: Vec<Node<'a>>, otherwise: Vec<Node<'a>> },
+}
+
+/// Tag that ends a list of nodes
+#[derive(PartialEq, Debug)]
+enum End<'a> {
+    Template,
+    Else,
+    Close(&'a str),
+}
+
+/// Parses nodes until the end of the template or a tag of the enclosing block
+fn parse<'a>(template: &mut &'a str) -> (Vec<Node<'a>>, End<'a>) {
+    let mut nodes = Vec::new();
+    loop {
+        let start = match template.find("{{") {
+            Some(start) => start,
+            None => {
+                if !template.is_empty() {
+                    nodes.push(Node::Text(template));
+                }
+                *template = "";
+                return (nodes, End::Template);
+            }
+        };
+        if start > 0 {
+            nodes.push(Node::Text(&template[..start]));
+        }
+
+        let (tag, escape) = match template[start..].strip_prefix("{{{") {
+            Some(rest) => {
+                let end = rest.find("}}}").expect("Template tag is not closed");
+                *template = &rest[end + 3..];
+                (rest[..end].trim(), false)
+            }
+            None => {
+                let rest = &template[start + 2..];
+                let end = rest.find("}}").expect("Template tag is not closed");
+                *template = &rest[end + 2..];
+                (rest[..end].trim(), true)
+            }
+        };
+
+        if let Some(name) = tag.strip_prefix("#each ") {
+            let (body, end) = parse(template);
+            assert_eq!(end, End::Close("each"), "Block `each` is not closed");
+            nodes.push(Node::Each { name: name.trim(), body });
+        } else if let Some(name) = tag.strip_prefix("#if ") {
+            let (then, end) = parse(template);
+            let otherwise = match end {
+                End::Else => {
+                    let (otherwise, end) = parse(template);
+                    assert_eq!(end, End::Close("if"), "Block `if` is not closed");
+                    otherwise
+                }
+                End::Close("if") => Vec::new(),
+                _ => panic!("Block `if` is not closed"),
+            };
+            nodes.push(Node::If { name: name.trim(), then, otherwise });
+        } else if tag == "else" {
+            return (nodes, End::Else);
+        } else if let Some(block) = tag.strip_prefix('/') {
+            return (nodes, End::Close(block.trim()));
+        } else {
+            nodes.push(Node::Variable { name: tag, escape });
+        }
+    }
+}
+
+/// Looks the variable up in the innermost scope first
+fn lookup<'a>(scopes: &[&'a Scope], name: &str) -> Option<&'a Value> {
+    scopes.iter().rev().find_map(|scope| scope.0.get(name))
+}
+
+fn render_nodes(nodes: &[Node], scopes: &mut Vec<&Scope>, output: &mut String) {
+    for node in nodes {
+        match node {
+            Node::Text(text) => output.push_str(text),
+            Node::Variable { name, escape } => match lookup(scopes, name) {
+                Some(Value::Text(text)) if *escape => output.push_str(&escape_html(text)),
+                Some(Value::Text(text)) | Some(Value::Html(text)) => output.push_str(text),
+                Some(Value::Bool(value)) => output.push_str(&value.to_string()),
+                Some(Value::List(_)) => panic!("Variable `{}` is a list", name),
+                None => panic!("Variable `{}` is not set", name),
+            },
+            Node::Each { name, body } => {
+                if let Some(Value::List(items)) = lookup(scopes, name) {
+                    for item in items {
+                        scopes.push(item);
+                        render_nodes(body, scopes, output);
+                        scopes.pop();
+                    }
+                }
+            }
+            Node::If { name, then, otherwise } => {
+                let is_truthy = matches!(lookup(scopes, name), Some(value) if value.is_truthy());
+                render_nodes(if is_truthy { then } else { otherwise }, scopes, output);
+            }
+        }
+    }
+}
+
+/// Renders the template with the variables of the scope.
+///
+/// `{{name}}` puts the escaped value of the variable, `{{{name}}}` puts it as it is.
+/// `{{#each name}}...{{/each}}` repeats the block for every scope of the list, variables
+/// of outer scopes stay available inside. `{{#if name}}...{{else}}...{{/if}}` renders the first
+/// block if the variable is true, non-empty text or a non-empty list. Panics on malformed
+/// templates and on variables that are not set, templates are a part of the contract
+pub(crate) fn render(template: &str, scope: &Scope) -> String {
+    let mut rest = template;
+    let (nodes, end) = parse(&mut rest);
+    assert_eq!(end, End::Template, "Template has an unexpected closing tag");
+
+    let mut output = String::with_capacity(template.len());
+    render_nodes(&nodes, &mut vec![scope], &mut output);
+    output
+}
+
+/// Escapes text written by users before it is put into a page, the result is safe
+/// both in element content and in quoted attribute values
+pub(crate) fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for char in text.chars() {
+        match char {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(char),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_escaping_of_task_text() {
+        let scope = Scope::new().text("task", "<script>alert(\"x\")</script> & more");
+
+        assert_eq!(
+            render("<td>{{task}}</td>", &scope),
+            "<td>&lt;script&gt;alert(&quot;x&quot;)&lt;/script&gt; &amp; more</td>"
+        );
+    }
+
+    #[test]
+    fn check_escaping_of_account_id() {
+        let scope = Scope::new().text("account_id", "lrn.testnet</h1><img src=x>");
+
+        assert_eq!(
+            render("<h1>Tasks of {{ account_id }}</h1>", &scope),
+            "<h1>Tasks of lrn.testnet&lt;/h1&gt;&lt;img src=x&gt;</h1>"
+        );
+    }
+
+    #[test]
+    fn check_escaping_in_attributes() {
+        let scope = Scope::new().text("uri", "x\" onmouseover='alert(1)'");
+
+        assert_eq!(
+            render("<a href=\"{{uri}}\" title='{{uri}}'>proof</a>", &scope),
+            "<a href=\"x&quot; onmouseover=&#39;alert(1)&#39;\" title='x&quot; onmouseover=&#39;alert(1)&#39;'>proof</a>"
+        );
+    }
+
+    #[test]
+    fn check_raw_values() {
+        let scope = Scope::new()
+            .html("stylesheet", "a > b { color: red; }")
+            .text("text", "a > b");
+
+        assert_eq!(
+            render("{{{stylesheet}}} {{{text}}}", &scope),
+            "a > b { color: red; } a > b"
+        );
+    }
+
+    #[test]
+    fn check_loops_and_conditionals() {
+        let scope = Scope::new().text("owner", "lrn.testnet").list(
+            "tasks",
+            vec![
+                Scope::new().text("task", "<b>").flag("done", true),
+                Scope::new().text("task", "b").flag("done", false),
+            ],
+        );
+        let template = "{{#each tasks}}[{{owner}}: {{task}} {{#if done}}done{{else}}open{{/if}}]{{/each}}\
+                        {{#if missing}}never{{/if}}{{#if tasks}}!{{/if}}";
+
+        assert_eq!(
+            render(template, &scope),
+            "[lrn.testnet: &lt;b&gt; done][lrn.testnet: b open]!"
+        );
+        assert_eq!(
+            render("{{#each tasks}}x{{/each}}{{#if tasks}}{{else}}empty{{/if}}", &Scope::new().list("tasks", vec![])),
+            "empty"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Block `each` is not closed")]
+    fn check_unclosed_block() {
+        render("{{#each tasks}}{{task}}", &Scope::new());
+    }
+}
@@ -0,0 +1,168 @@
+use crate::*;
+
+use near_contract_standards::fungible_token::core::ext_ft_core;
+use near_contract_standards::fungible_token::receiver::FungibleTokenReceiver;
+use near_sdk::serde_json;
+use near_sdk::{is_promise_success, Gas, PromiseOrValue, ONE_YOCTO};
+
+pub(crate) const GAS_FOR_FT_TRANSFER: Gas = Gas(10_000_000_000_000);
+pub(crate) const GAS_FOR_FT_TRANSFER_CALLBACK: Gas = Gas(10_000_000_000_000);
+
+#[near_bindgen]
+impl FungibleTokenReceiver for Contract {
+    /// Creates a task backed by the tokens of a whitelisted contract,
+    /// the transfer is returned to the sender if the task can not be created
+    fn ft_on_transfer(
+        &mut self,
+        sender_id: AccountId,
+        amount: U128,
+        msg: String,
+    ) -> PromiseOrValue<U128> {
+        let token_id = env::predecessor_account_id();
+        let min_deposit = self
+            .config
+            .token_min_deposits
+            .get(&token_id)
+            .expect("Token is not accepted as a deposit");
+        assert!(
+            amount.0 >= min_deposit.0,
+            "For creation task you need pay minimum {} of {}",
+            min_deposit.0,
+            token_id
+        );
+        if let Some(max_deposit) = self.config.token_max_deposits.get(&token_id) {
+            assert!(
+                amount.0 <= max_deposit.0,
+                "For creation task you can pay maximum {} of {}",
+                max_deposit.0,
+                token_id
+            );
+        }
+        // The message is a task like `{"task": "Write a post", "deadline_time": {"relative": "3d"}}`
+        let new_task: NewTask = serde_json::from_str(&msg).expect("Message is not a valid task");
+
+        self.internal_create_task(sender_id, new_task, amount.0, Some(token_id));
+        PromiseOrValue::Value(U128(0))
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Returns refunded deposits paid in tokens that the user can claim
+    pub fn get_claimable_tokens(&self, account_id: AccountId) -> HashMap<AccountId, U128> {
+        self.get_user_records(&account_id)
+            .map(|user_records| {
+                user_records
+                    .claimable_tokens
+                    .into_iter()
+                    .map(|(token_id, amount)| (token_id, U128(amount)))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Returns the ledger of deposits paid in the token
+    pub fn get_token_treasury_stats(&self, token_id: AccountId) -> TreasuryStats {
+        TreasuryStats::from(&self.token_treasuries.get(&token_id).unwrap_or_default())
+    }
+
+    /// Sends all refunded deposits of the user paid in the token with `ft_transfer`,
+    /// to the beneficiary if it is given or to the user otherwise
+    #[payable]
+    pub fn claim_token_refunds(
+        &mut self,
+        token_id: AccountId,
+        beneficiary: Option<AccountId>,
+    ) -> Promise {
+        near_sdk::assert_one_yocto();
+        let account_id = env::predecessor_account_id();
+        self.assert_migrated(&account_id);
+        let mut user_records = self.get_user_records(&account_id).expect("User not found");
+        let amount = user_records
+            .claimable_tokens
+            .remove(&token_id)
+            .unwrap_or(0);
+        assert!(amount > 0, "Nothing to claim");
+
+        self.set_user_records(&account_id, user_records);
+        self.update_treasury(Some(&token_id), |treasury| treasury.total_claimed += amount);
+
+        ext_ft_core::ext(token_id.clone())
+            .with_attached_deposit(ONE_YOCTO)
+            .with_static_gas(GAS_FOR_FT_TRANSFER)
+            .ft_transfer(
+                beneficiary.unwrap_or_else(|| account_id.clone()),
+                U128(amount),
+                None,
+            )
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_FT_TRANSFER_CALLBACK)
+                    .on_claim_token_refunds_complete(account_id, token_id, U128(amount)),
+            )
+    }
+
+    /// Restores the claimable refunds in the token if the transfer failed
+    #[private]
+    pub fn on_claim_token_refunds_complete(
+        &mut self,
+        account_id: AccountId,
+        token_id: AccountId,
+        amount: U128,
+    ) -> bool {
+        if !is_promise_success() {
+            let mut user_records = self.get_user_records(&account_id).expect("User not found");
+            user_records.add_claimable(Some(&token_id), amount.0);
+            self.set_user_records(&account_id, user_records);
+            self.update_treasury(Some(&token_id), |treasury| treasury.total_claimed -= amount.0);
+            return false;
+        }
+        true
+    }
+
+    /// Sends withheld deposits paid in the token to the receiver, can be called only by the owner
+    #[payable]
+    pub fn withdraw_withheld_token(
+        &mut self,
+        token_id: AccountId,
+        amount: U128,
+        receiver: AccountId,
+    ) -> Promise {
+        near_sdk::assert_one_yocto();
+        self.assert_owner();
+        assert!(amount.0 > 0, "Amount must be positive");
+        let available = self
+            .token_treasuries
+            .get(&token_id)
+            .map(|treasury| treasury.available_withheld())
+            .unwrap_or(0);
+        assert!(
+            amount.0 <= available,
+            "Only {} of withheld deposits in {} can be withdrawn",
+            available,
+            token_id
+        );
+
+        self.update_treasury(Some(&token_id), |treasury| treasury.total_withdrawn += amount.0);
+
+        ext_ft_core::ext(token_id.clone())
+            .with_attached_deposit(ONE_YOCTO)
+            .with_static_gas(GAS_FOR_FT_TRANSFER)
+            .ft_transfer(receiver, amount, None)
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_FT_TRANSFER_CALLBACK)
+                    .on_withdraw_withheld_token_complete(token_id, amount),
+            )
+    }
+
+    /// Returns the amount to the ledger of the token if the transfer failed
+    #[private]
+    pub fn on_withdraw_withheld_token_complete(&mut self, token_id: AccountId, amount: U128) -> bool {
+        if !is_promise_success() {
+            self.update_treasury(Some(&token_id), |treasury| treasury.total_withdrawn -= amount.0);
+            return false;
+        }
+        true
+    }
+}
@@ -0,0 +1,128 @@
+use crate::*;
+
+use near_sdk::json_types::U128;
+use near_sdk::{is_promise_success, Gas};
+
+const GAS_FOR_WITHDRAW_CALLBACK: Gas = Gas(10_000_000_000_000);
+
+/// Ledger of deposits that passed through the contract, in yoctoNear or in the smallest units of a token
+#[derive(BorshDeserialize, BorshSerialize, Default)]
+pub struct Treasury {
+    /// Deposits paid for creation of tasks
+    pub total_contributed: Balance,
+    /// Deposits of tasks completed in time credited to claimable refunds of users
+    pub total_refunded: Balance,
+    /// Refunds transferred to users by `claim_refunds`
+    pub total_claimed: Balance,
+    /// Deposits of failed tasks that stayed in the service
+    pub total_withheld: Balance,
+    /// Withheld deposits moved out of the contract by the owner
+    pub total_withdrawn: Balance,
+    /// Parts of withheld deposits paid to callers of `settle_expired_tasks`
+    pub total_keeper_fees: Balance,
+    /// Withheld deposits sent to beneficiaries of failed tasks
+    pub total_donated: Balance,
+}
+
+impl Treasury {
+    /// Withheld deposits that can still be withdrawn by the owner
+    pub fn available_withheld(&self) -> Balance {
+        self.total_withheld - self.total_keeper_fees - self.total_withdrawn - self.total_donated
+    }
+
+    /// Refunds that are still kept by the contract until users claim them
+    pub fn unclaimed_refunds(&self) -> Balance {
+        self.total_refunded - self.total_claimed
+    }
+
+    /// Deposits of tasks that are neither refunded nor withheld yet
+    pub fn outstanding_deposits(&self) -> Balance {
+        self.total_contributed - self.total_refunded - self.total_withheld
+    }
+
+    /// Takes into account a record that was created before the ledger existed
+    pub(crate) fn add_legacy_record(&mut self, record: &Record) {
+        self.total_contributed += record.guarantee_of_task_completion;
+        match record.deposit_status {
+            DepositStatus::Contributed => {}
+            // The first two versions transferred refunds right away
+            DepositStatus::Refunded => {
+                self.total_refunded += record.guarantee_of_task_completion;
+                self.total_claimed += record.guarantee_of_task_completion;
+            }
+            DepositStatus::Withheld => self.total_withheld += record.guarantee_of_task_completion,
+            // Tasks could not be cancelled in the first two versions
+            DepositStatus::Cancelled => {}
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct TreasuryStats {
+    pub total_contributed: U128,
+    pub total_refunded: U128,
+    pub total_claimed: U128,
+    pub total_withheld: U128,
+    pub total_withdrawn: U128,
+    pub total_keeper_fees: U128,
+    pub total_donated: U128,
+    pub available_withheld: U128,
+    pub unclaimed_refunds: U128,
+    pub outstanding_deposits: U128,
+}
+
+impl From<&Treasury> for TreasuryStats {
+    fn from(treasury: &Treasury) -> Self {
+        Self {
+            total_contributed: U128(treasury.total_contributed),
+            total_refunded: U128(treasury.total_refunded),
+            total_claimed: U128(treasury.total_claimed),
+            total_withheld: U128(treasury.total_withheld),
+            total_withdrawn: U128(treasury.total_withdrawn),
+            total_keeper_fees: U128(treasury.total_keeper_fees),
+            total_donated: U128(treasury.total_donated),
+            available_withheld: U128(treasury.available_withheld()),
+            unclaimed_refunds: U128(treasury.unclaimed_refunds()),
+            outstanding_deposits: U128(treasury.outstanding_deposits()),
+        }
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Returns the ledger of deposits for reconciliation with the contract balance
+    pub fn get_treasury_stats(&self) -> TreasuryStats {
+        TreasuryStats::from(&self.treasury)
+    }
+
+    /// Sends withheld deposits to the receiver, deposits of tasks that are not settled yet
+    /// can not be withdrawn. Can be called only by the owner
+    pub fn withdraw_withheld(&mut self, amount: U128, receiver: AccountId) -> Promise {
+        self.assert_owner();
+        assert!(amount.0 > 0, "Amount must be positive");
+        assert!(
+            amount.0 <= self.treasury.available_withheld(),
+            "Only {} yoctoNEAR of withheld deposits can be withdrawn",
+            self.treasury.available_withheld()
+        );
+
+        self.treasury.total_withdrawn += amount.0;
+
+        Promise::new(receiver).transfer(amount.0).then(
+            Self::ext(env::current_account_id())
+                .with_static_gas(GAS_FOR_WITHDRAW_CALLBACK)
+                .on_withdraw_withheld_complete(amount),
+        )
+    }
+
+    /// Returns the amount to the treasury if the transfer failed
+    #[private]
+    pub fn on_withdraw_withheld_complete(&mut self, amount: U128) -> bool {
+        if !is_promise_success() {
+            self.treasury.total_withdrawn -= amount.0;
+            return false;
+        }
+        true
+    }
+}
@@ -0,0 +1,301 @@
+use crate::*;
+
+use crate::achievement::default_milestones;
+use crate::deadline::normalize_legacy_timestamp;
+use crate::events::{Event, LegacyRecordDisputedData};
+use near_sdk::{Gas, IntoStorageKey};
+
+/// Key of the version of the stored contract state,
+/// the state of the first release was written without it
+pub(crate) const STATE_VERSION_KEY: &[u8] = b"STATE_VERSION";
+/// Version of the layout of `Contract`, `UserRecords` and `Record`. Version 2 added the owner and
+/// versioned records, version 3 added the config, ledgers, stats and fields of tasks. A change of
+/// any of these layouts bumps the version and adds a variant for the previous layout
+pub(crate) const STATE_VERSION: u8 = 3;
+
+const GAS_FOR_MIGRATE: Gas = Gas(50_000_000_000_000);
+
+/// Contract state of the first release
+#[derive(BorshDeserialize)]
+pub struct ContractV1 {
+    pub common_records: LookupMap<AccountId, UserRecordsV1>,
+}
+
+/// User records of the first release, task maps of all users were stored under one shared prefix
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct UserRecordsV1 {
+    pub user_records: UnorderedMap<i64, RecordV1>,
+    pub record_id: i64,
+}
+
+/// Reads the record of the shared task map by its id. Keys, values and their indexes are shared
+/// by all users and the length stored in the map of a user does not cover records written by others,
+/// so the record is read from the storage directly
+fn get_shared_record(record_id: i64) -> Option<RecordV1> {
+    let prefix = StorageKey::UserRecords.into_storage_key();
+    let index_key = [&prefix[..], b"i", &record_id.try_to_vec().unwrap()].concat();
+    let index = u64::try_from_slice(&env::storage_read(&index_key)?).unwrap();
+    let value_key = [&prefix[..], b"v", &index.to_le_bytes()].concat();
+    env::storage_read(&value_key).map(|value| RecordV1::try_from_slice(&value).unwrap())
+}
+
+/// Contract state of version 2
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct ContractV2 {
+    pub owner_id: AccountId,
+    pub common_records: LookupMap<AccountId, VersionedUserRecords>,
+    pub legacy_records: LookupMap<AccountId, UserRecordsV1>,
+}
+
+/// User records of version 2, stored under the prefix of the account like the current ones
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct UserRecordsV2 {
+    pub user_records: UnorderedMap<i64, VersionedRecord>,
+    pub record_id: i64,
+}
+
+impl UserRecordsV2 {
+    /// Converts records of the account to the current layout,
+    /// the stats of the user are counted from the records
+    pub(crate) fn into_current(self, account_id: &AccountId) -> UserRecords {
+        let mut user_records = UserRecords::new(account_id);
+        user_records.user_records = self.user_records;
+        user_records.record_id = self.record_id;
+        for (_, record) in user_records.records().collect::<Vec<_>>() {
+            add_legacy_stats(&mut user_records.stats, &record);
+        }
+        user_records
+    }
+}
+
+/// Counts a record of the first two versions in the stats of the user
+fn add_legacy_stats(stats: &mut UserStats, record: &Record) {
+    stats.add_created(None, record.guarantee_of_task_completion);
+    match record.deposit_status {
+        DepositStatus::Refunded => stats.add_completed_on_time(None, record.guarantee_of_task_completion),
+        DepositStatus::Withheld => {
+            if record.is_complete_status {
+                stats.add_completed_late();
+            }
+            stats.add_withheld(None, record.guarantee_of_task_completion);
+        }
+        DepositStatus::Contributed | DepositStatus::Cancelled => {}
+    }
+}
+
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct RecordV1 {
+    pub task: String,
+    pub is_complete_status: bool,
+    pub guarantee_of_task_completion: u128,
+    pub deadline_time: Timestamp,
+    pub account_balance: Balance,
+    pub deposit_status: DepositStatus,
+}
+
+/// Every layout of the contract state that may be found in the storage
+pub enum VersionedContract {
+    V1(ContractV1),
+    V2(ContractV2),
+    Current(Box<Contract>),
+}
+
+impl VersionedContract {
+    /// Reads the contract state in the layout of the stored version
+    fn read() -> Self {
+        let version = env::storage_read(STATE_VERSION_KEY)
+            .map(|version| version[0])
+            .unwrap_or(1);
+        match version {
+            1 => VersionedContract::V1(env::state_read().expect("Contract is not initialized")),
+            2 => VersionedContract::V2(env::state_read().expect("Contract is not initialized")),
+            STATE_VERSION => {
+                VersionedContract::Current(Box::new(
+                    env::state_read().expect("Contract is not initialized"),
+                ))
+            }
+            _ => env::panic_str("Unknown version of the contract state"),
+        }
+    }
+}
+
+impl From<VersionedContract> for Contract {
+    fn from(contract: VersionedContract) -> Self {
+        match contract {
+            VersionedContract::V1(contract) => Self::with_records(
+                // The first release had no owner, it could only be deployed by the contract account
+                env::current_account_id(),
+                LookupMap::new(StorageKey::VersionedCommonRecords),
+                contract.common_records,
+            ),
+            VersionedContract::V2(contract) => {
+                Self::with_records(contract.owner_id, contract.common_records, contract.legacy_records)
+            }
+            VersionedContract::Current(contract) => *contract,
+        }
+    }
+}
+
+impl Contract {
+    /// Creates the state around records of users of an older version,
+    /// everything added after version 2 starts empty
+    fn with_records(
+        owner_id: AccountId,
+        common_records: LookupMap<AccountId, VersionedUserRecords>,
+        legacy_records: LookupMap<AccountId, UserRecordsV1>,
+    ) -> Self {
+        Self {
+            owner_id,
+            config: Config::default(),
+            paused: false,
+            treasury: Treasury::default(),
+            common_records,
+            legacy_records,
+            token_treasuries: LookupMap::new(StorageKey::TokenTreasuries),
+            leaderboards: LookupMap::new(StorageKey::Leaderboards),
+            challenges: UnorderedMap::new(StorageKey::Challenges),
+            challenge_id: 1,
+            nft: NonFungibleToken::new(
+                StorageKey::NftOwners,
+                env::current_account_id(),
+                Some(StorageKey::NftMetadata),
+                Some(StorageKey::NftEnumeration),
+                None::<StorageKey>,
+            ),
+            milestones: default_milestones(),
+            soulbound_tokens: LookupSet::new(StorageKey::SoulboundTokens),
+        }
+    }
+}
+
+impl From<RecordV1> for Record {
+    fn from(record: RecordV1) -> Self {
+        Self {
+            task: record.task,
+            is_complete_status: record.is_complete_status,
+            guarantee_of_task_completion: record.guarantee_of_task_completion,
+            deadline_time: normalize_legacy_timestamp(record.deadline_time),
+            account_balance: record.account_balance,
+            deposit_status: record.deposit_status,
+            // The first release did not store the time of creation
+            created_at: 0,
+            history: Vec::new(),
+            token_id: None,
+            verifier: None,
+            beneficiary: None,
+            completion_claim: None,
+            proofs: Vec::new(),
+            recurring_id: None,
+        }
+    }
+}
+
+impl Contract {
+    /// Stops calls of accounts whose tasks are still in the layout of an older version.
+    /// Every user of the first release numbered tasks from 1 in the shared map, so the owner
+    /// decides the order accounts are migrated in with `migrate_user_records`. Deposits of
+    /// version 2 are added to the ledger by the same migration
+    pub(crate) fn assert_migrated(&self, account_id: &AccountId) {
+        assert!(
+            !self.legacy_records.contains_key(account_id)
+                && !matches!(self.common_records.get(account_id), Some(VersionedUserRecords::V2(_))),
+            "Tasks of the account are waiting for migration by the owner"
+        );
+    }
+
+    /// Moves records of the user from an older layout to the current one,
+    /// returns false if there was nothing to migrate
+    fn migrate_account(&mut self, account_id: &AccountId) -> bool {
+        if self.legacy_records.contains_key(account_id) {
+            self.migrate_legacy_account(account_id);
+            return true;
+        }
+        let user_records = match self.common_records.get(account_id) {
+            Some(VersionedUserRecords::V2(user_records)) => user_records.into_current(account_id),
+            _ => return false,
+        };
+        for (_, record) in user_records.records() {
+            self.treasury.add_legacy_record(&record);
+        }
+        self.set_user_records(account_id, user_records);
+        true
+    }
+
+    /// Moves records of the user from the shared map of the first release.
+    ///
+    /// Records are read by ids from 1 to `record_id` of the user. A record id is given to
+    /// the first migrated account that has it, later accounts with the same id do not get
+    /// the record and a `legacy_record_disputed` event is emitted for the owner to sort
+    /// the deposit out
+    fn migrate_legacy_account(&mut self, account_id: &AccountId) {
+        let legacy_records = self.legacy_records.remove(account_id).unwrap();
+        let mut claims: LookupMap<i64, AccountId> = LookupMap::new(StorageKey::LegacyRecordClaims);
+
+        let mut user_records = UserRecords::new(account_id);
+        for record_id in 1..legacy_records.record_id {
+            let record = match get_shared_record(record_id) {
+                Some(record) => record,
+                None => continue,
+            };
+            if let Some(claimed_by) = claims.get(&record_id) {
+                Event::LegacyRecordDisputed(vec![LegacyRecordDisputedData {
+                    account_id: account_id.clone(),
+                    record_id,
+                    claimed_by,
+                }])
+                .emit();
+                continue;
+            }
+            claims.insert(&record_id, account_id);
+
+            let versioned_record = VersionedRecord::V1(record);
+            user_records.user_records.insert(&record_id, &versioned_record);
+
+            let record = Record::from(versioned_record);
+            self.treasury.add_legacy_record(&record);
+            add_legacy_stats(&mut user_records.stats, &record);
+        }
+        user_records.record_id = legacy_records.record_id;
+
+        self.set_user_records(account_id, user_records);
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Converts the stored state to the current layout, is called by `upgrade` after the new code
+    /// is deployed. Records of users are migrated separately by `migrate_user_records`.
+    #[private]
+    #[init(ignore_state)]
+    pub fn migrate() -> Self {
+        let contract = Contract::from(VersionedContract::read());
+        env::storage_write(STATE_VERSION_KEY, &[STATE_VERSION]);
+        contract
+    }
+
+    /// Deploys the code passed as the raw input of the call and migrates the state
+    pub fn upgrade(&self) -> Promise {
+        self.assert_owner();
+        let code = env::input().expect("Code of the contract is not provided");
+        Promise::new(env::current_account_id())
+            .deploy_contract(code)
+            .function_call("migrate".to_string(), Vec::new(), 0, GAS_FOR_MIGRATE)
+    }
+
+    /// One-shot migration of task maps to per-account prefixes with versioned records
+    /// and of records of version 2 to the current layout.
+    /// Accounts are passed in batches because the legacy users list can not be iterated,
+    /// already migrated accounts are skipped. Accounts are migrated only by this method,
+    /// a task id shared by several accounts goes to the one passed first.
+    /// Returns the number of migrated accounts.
+    pub fn migrate_user_records(&mut self, account_ids: Vec<AccountId>) -> u32 {
+        self.assert_owner();
+        let mut migrated = 0;
+        for account_id in account_ids {
+            if self.migrate_account(&account_id) {
+                migrated += 1;
+            }
+        }
+        migrated
+    }
+}
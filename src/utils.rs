@@ -1,20 +1,6 @@
 use crate::*;
 
-pub(crate) fn unordered_map_pagination<K, VV, V>(
-    m: &UnorderedMap<K, VV>,
-    from_index: Option<u64>,
-    limit: Option<u64>,
-) -> Vec<(K, V)>
-    where
-        K: BorshSerialize + BorshDeserialize,
-        VV: BorshSerialize + BorshDeserialize,
-        V: From<VV>,
-{
-    let keys = m.keys_as_vector();
-    let values = m.values_as_vector();
-    let from_index = from_index.unwrap_or(0);
-    let limit = limit.unwrap_or(keys.len());
-    (from_index..std::cmp::min(keys.len(), from_index + limit))
-        .map(|index| (keys.get(index).unwrap(), values.get(index).unwrap().into()))
-        .collect()
+/// Returns the share of the amount given in basis points
+pub(crate) fn bps_share(amount: Balance, bps: u16) -> Balance {
+    amount * bps as Balance / crate::config::MAX_BPS as Balance
 }
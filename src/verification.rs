@@ -0,0 +1,180 @@
+use crate::*;
+
+use crate::events::{CompletionClaimedData, CompletionResolvedData, Event, TaskCompletedData};
+
+/// Completion of a task submitted by its owner to the verifier
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct CompletionClaim {
+    /// URL or hash of the content that proves completion of the task
+    pub proof: String,
+    /// Time of the claim, in nanoseconds
+    pub claimed_at: Timestamp,
+    pub status: ClaimStatus,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum ClaimStatus {
+    Pending,
+    Approved,
+    Rejected,
+}
+
+/// How a completion claim is resolved if the verifier does not answer in time
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum VerificationOutcome {
+    Approve,
+    Reject,
+}
+
+impl Record {
+    /// Checks whether a completion claim of the task is waiting for the verifier
+    pub fn has_pending_claim(&self) -> bool {
+        matches!(&self.completion_claim, Some(claim) if claim.status == ClaimStatus::Pending)
+    }
+}
+
+impl Contract {
+    /// Approves or rejects the pending claim, an approved task is completed and its deposit
+    /// is refunded, the deposit of a rejected task is withheld
+    fn resolve_claim(
+        &mut self,
+        account_id: &AccountId,
+        record_id: i64,
+        outcome: VerificationOutcome,
+        by_timeout: bool,
+    ) {
+        let mut user_records = self.get_user_records(account_id).expect("User not found");
+        let mut record = user_records.get_record(record_id).expect("Task not found");
+        assert!(record.has_pending_claim(), "Task has no completion claim to resolve");
+        assert!(
+            record.deposit_status == DepositStatus::Contributed,
+            "Deposit of the task is already settled"
+        );
+
+        let claim = record.completion_claim.as_mut().unwrap();
+        match outcome {
+            VerificationOutcome::Approve => {
+                claim.status = ClaimStatus::Approved;
+                record.is_complete_status = true;
+
+                Event::TaskCompleted(vec![TaskCompletedData {
+                    account_id: account_id.clone(),
+                    record_id,
+                    on_time: true,
+                }])
+                .emit();
+                self.refund_deposit(account_id, record_id, &mut record, &mut user_records);
+            }
+            VerificationOutcome::Reject => {
+                claim.status = ClaimStatus::Rejected;
+                let donation = self.withhold_deposit(account_id, record_id, &mut record, &mut user_records, 0);
+                self.send_donations(donation);
+            }
+        }
+
+        Event::CompletionResolved(vec![CompletionResolvedData {
+            account_id: account_id.clone(),
+            record_id,
+            verifier_id: record.verifier.clone().unwrap(),
+            approved: outcome == VerificationOutcome::Approve,
+            by_timeout,
+        }])
+        .emit();
+
+        user_records.set_record(record_id, &record);
+        self.set_user_records(account_id, user_records);
+    }
+
+    fn assert_verifier(&self, account_id: &AccountId, record_id: i64) {
+        let record = self
+            .get_user_records(account_id)
+            .expect("User not found")
+            .get_record(record_id)
+            .expect("Task not found");
+        assert_eq!(
+            record.verifier,
+            Some(env::predecessor_account_id()),
+            "Only the verifier of the task can call this method"
+        );
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Submits completion of a task with a verifier before the deadline or after it
+    /// if a proof was submitted in time, the deposit is settled when the verifier
+    /// approves or rejects the claim
+    pub fn claim_completion(&mut self, record_id: i64, proof: String) {
+        let account_id = env::predecessor_account_id();
+        self.assert_migrated(&account_id);
+        let mut user_records = self.get_user_records(&account_id).expect("User not found");
+        let mut record = user_records.get_record(record_id).expect("Task not found");
+
+        let verifier_id = record.verifier.clone().expect("Task has no verifier");
+        assert!(
+            !record.is_complete_status && record.deposit_status == DepositStatus::Contributed,
+            "Only tasks in progress can be claimed"
+        );
+        assert!(!record.has_pending_claim(), "Completion of the task is already claimed");
+        assert!(
+            record.is_on_time(),
+            "Deadline was ended, completion can not be claimed"
+        );
+        assert!(
+            !proof.is_empty() && proof.len() <= self.config.max_task_length as usize,
+            "Proof must be from 1 to {} bytes",
+            self.config.max_task_length
+        );
+
+        record.completion_claim = Some(CompletionClaim {
+            proof: proof.clone(),
+            claimed_at: env::block_timestamp(),
+            status: ClaimStatus::Pending,
+        });
+        user_records.set_record(record_id, &record);
+        self.set_user_records(&account_id, user_records);
+
+        Event::CompletionClaimed(vec![CompletionClaimedData {
+            account_id,
+            record_id,
+            verifier_id,
+            proof,
+        }])
+        .emit();
+    }
+
+    /// Confirms the completion claim, the deposit is added to the claimable refunds of the owner.
+    /// Can be called only by the verifier of the task
+    pub fn approve_completion(&mut self, account_id: AccountId, record_id: i64) {
+        self.assert_verifier(&account_id, record_id);
+        self.resolve_claim(&account_id, record_id, VerificationOutcome::Approve, false);
+    }
+
+    /// Rejects the completion claim, the deposit stays in the service.
+    /// Can be called only by the verifier of the task
+    pub fn reject_completion(&mut self, account_id: AccountId, record_id: i64) {
+        self.assert_verifier(&account_id, record_id);
+        self.resolve_claim(&account_id, record_id, VerificationOutcome::Reject, false);
+    }
+
+    /// Resolves a claim the verifier did not answer within the verification timeout
+    /// with the default outcome of the config. Anyone can call the method
+    pub fn resolve_expired_claim(&mut self, account_id: AccountId, record_id: i64) {
+        let claim = self
+            .get_user_records(&account_id)
+            .expect("User not found")
+            .get_record(record_id)
+            .expect("Task not found")
+            .completion_claim
+            .expect("Task has no completion claim to resolve");
+        assert!(
+            env::block_timestamp() >= claim.claimed_at + self.config.verification_timeout.0,
+            "Verifier can still answer the claim"
+        );
+        let outcome = self.config.verification_default;
+        self.resolve_claim(&account_id, record_id, outcome, true);
+    }
+}
@@ -0,0 +1,109 @@
+use crate::*;
+
+use near_sdk::json_types::U64;
+
+/// Number of tasks returned by `get_user_tasks` if `limit` is not set
+pub const DEFAULT_TASKS_LIMIT: u64 = 20;
+/// Maximal number of tasks returned by `get_user_tasks`
+pub const MAX_TASKS_LIMIT: u64 = 100;
+/// Maximal number of tasks read by `get_user_task_count` to count tasks matching a filter
+pub const MAX_COUNTED_TASKS: usize = 500;
+
+/// Conditions that selected tasks must meet, conditions that are not set are not checked
+#[derive(Serialize, Deserialize, Default, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct TaskFilter {
+    pub is_complete: Option<bool>,
+    pub deposit_status: Option<DepositStatus>,
+    /// Earliest deadline of selected tasks, inclusive, in nanoseconds
+    pub deadline_from: Option<U64>,
+    /// Latest deadline of selected tasks, inclusive, in nanoseconds
+    pub deadline_to: Option<U64>,
+    /// Selects tasks that are or are not overdue, see `Record::is_overdue`
+    pub overdue: Option<bool>,
+}
+
+impl TaskFilter {
+    /// Checks whether no condition is set, such a filter selects every task
+    pub fn is_empty(&self) -> bool {
+        self.is_complete.is_none()
+            && self.deposit_status.is_none()
+            && self.deadline_from.is_none()
+            && self.deadline_to.is_none()
+            && self.overdue.is_none()
+    }
+
+    pub fn matches(&self, record: &Record) -> bool {
+        if let Some(is_complete) = self.is_complete {
+            if record.is_complete_status != is_complete {
+                return false;
+            }
+        }
+        if let Some(deposit_status) = &self.deposit_status {
+            if &record.deposit_status != deposit_status {
+                return false;
+            }
+        }
+        if let Some(deadline_from) = self.deadline_from {
+            if record.deadline_time < deadline_from.0 {
+                return false;
+            }
+        }
+        if let Some(deadline_to) = self.deadline_to {
+            if record.deadline_time > deadline_to.0 {
+                return false;
+            }
+        }
+        if let Some(overdue) = self.overdue {
+            if record.is_overdue() != overdue {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Returns tasks of the user ordered by id that match the filter,
+    /// `from_index` and `limit` are counted among the matched tasks. Tasks are read
+    /// in the order of ids until `limit` of them are matched, `limit` is
+    /// `DEFAULT_TASKS_LIMIT` by default and at most `MAX_TASKS_LIMIT`.
+    /// Returns an empty list if the user has no tasks
+    pub fn get_user_tasks(
+        &self,
+        user_id: AccountId,
+        from_index: Option<u64>,
+        limit: Option<u64>,
+        filter: Option<TaskFilter>,
+    ) -> Vec<(i64, Record)> {
+        let user_records = match self.get_user_records(&user_id) {
+            Some(user_records) => user_records,
+            None => return Vec::new(),
+        };
+        let filter = filter.unwrap_or_default();
+        user_records
+            .records()
+            .filter(|(_, record)| filter.matches(record))
+            .skip(from_index.unwrap_or(0) as usize)
+            .take(std::cmp::min(limit.unwrap_or(DEFAULT_TASKS_LIMIT), MAX_TASKS_LIMIT) as usize)
+            .collect()
+    }
+
+    /// Returns the number of tasks of the user that match the filter. With a filter only
+    /// the first `MAX_COUNTED_TASKS` tasks of the user are read
+    pub fn get_user_task_count(&self, user_id: AccountId, filter: Option<TaskFilter>) -> u64 {
+        let user_records = match self.get_user_records(&user_id) {
+            Some(user_records) => user_records,
+            None => return 0,
+        };
+        match filter {
+            Some(filter) if !filter.is_empty() => user_records
+                .records()
+                .take(MAX_COUNTED_TASKS)
+                .filter(|(_, record)| filter.matches(record))
+                .count() as u64,
+            _ => user_records.user_records.len(),
+        }
+    }
+}
@@ -1,10 +1,20 @@
 use crate::*;
 
+use crate::achievement::achievement_svg;
+use crate::api::ApiError;
+use crate::template::{render, Scope};
+use near_contract_standards::non_fungible_token::core::NonFungibleTokenCore;
+
 use near_sdk::env;
 use near_sdk::json_types::Base64VecU8;
 use std::collections::HashMap;
+use std::str::FromStr;
 
 const STYLES_BODY: &str = include_str!("../res/style.css");
+/// Default number of tasks on a page of the dashboard
+const TASKS_PAGE_SIZE: u64 = 20;
+/// Maximal number of tasks on a page that can be requested with `limit`
+const MAX_TASKS_PAGE_SIZE: u64 = 100;
 
 #[allow(dead_code)]
 #[derive(Serialize, Deserialize)]
@@ -48,6 +58,48 @@ impl Web4Response {
         }
     }
 
+    pub fn svg_response(text: String) -> Self {
+        Self {
+            content_type: Some(String::from("image/svg+xml")),
+            body: Some(text.into_bytes().into()),
+            ..Default::default()
+        }
+    }
+
+    pub fn calendar_response(text: String) -> Self {
+        Self {
+            content_type: Some(String::from("text/calendar; charset=UTF-8")),
+            body: Some(text.into_bytes().into()),
+            ..Default::default()
+        }
+    }
+
+    pub fn json_response<T: Serialize>(value: &T) -> Self {
+        Self {
+            content_type: Some(String::from("application/json; charset=UTF-8")),
+            body: Some(near_sdk::serde_json::to_vec(value).unwrap().into()),
+            ..Default::default()
+        }
+    }
+
+    /// Responds with the status and a JSON body like `{"error": "Not found"}`
+    pub fn json_error(status: u32, error: &str) -> Self {
+        Self {
+            status: Some(status),
+            ..Self::json_response(&ApiError {
+                error: error.to_string(),
+            })
+        }
+    }
+
+    /// Responds with the status 404 and the not-found page
+    pub fn not_found() -> Self {
+        Self {
+            status: Some(404),
+            ..Self::html_response(render(include_str!("../res/404.html"), &page_scope()))
+        }
+    }
+
     pub fn preload_urls(urls: Vec<String>) -> Self {
         Self {
             preload_urls: Some(urls),
@@ -70,55 +122,417 @@ impl Web4Response {
     }
 }
 
+/// Values of the `{name}` segments of a route pattern taken from the path
+#[derive(Default)]
+pub(crate) struct PathParams(HashMap<String, String>);
+
+impl PathParams {
+    /// Returns the parameter parsed into the type, `None` if it is missing or malformed
+    pub(crate) fn get<T: FromStr>(&self, name: &str) -> Option<T> {
+        self.0.get(name)?.parse().ok()
+    }
+}
+
+/// Parameters of the query string, the first value wins if a name is repeated
+#[derive(Default)]
+pub(crate) struct QueryParams(HashMap<String, Vec<String>>);
+
+impl QueryParams {
+    /// Collects parameters passed by the gateway and the ones left in the path after `?`
+    fn new(request: &Web4Request, query_string: Option<&str>) -> Self {
+        let mut query = request.query.clone().unwrap_or_default();
+        if let Some(params) = &request.params {
+            for (name, value) in params {
+                query.entry(name.clone()).or_default().push(value.clone());
+            }
+        }
+        for pair in query_string.unwrap_or("").split('&').filter(|pair| !pair.is_empty()) {
+            let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+            query
+                .entry(decode_query_component(name))
+                .or_default()
+                .push(decode_query_component(value));
+        }
+        Self(query)
+    }
+
+    /// Returns the parameter, `Ok(None)` if it is missing and `Err` if it is malformed
+    pub(crate) fn get<T: FromStr>(&self, name: &str) -> Result<Option<T>, ()> {
+        match self.0.get(name).and_then(|values| values.first()) {
+            Some(value) => value.parse().map(Some).map_err(|_| ()),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Decodes `+` and `%XX` escapes of a query string, malformed escapes are kept as they are
+fn decode_query_component(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        match bytes[index] {
+            b'+' => decoded.push(b' '),
+            b'%' if index + 2 < bytes.len()
+                && bytes[index + 1].is_ascii_hexdigit()
+                && bytes[index + 2].is_ascii_hexdigit() =>
+            {
+                decoded.push(u8::from_str_radix(&text[index + 1..index + 3], 16).unwrap());
+                index += 2;
+            }
+            byte => decoded.push(byte),
+        }
+        index += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Matches the path against a pattern like `/u/{account}/task/{id}`, a placeholder takes
+/// the rest of its segment except the text around it, e.g. `{id}.svg`
+pub(crate) fn match_route(pattern: &str, path: &str) -> Option<PathParams> {
+    let pattern_segments: Vec<&str> = pattern.split('/').collect();
+    let path_segments: Vec<&str> = path.split('/').collect();
+    if pattern_segments.len() != path_segments.len() {
+        return None;
+    }
+
+    let mut params = PathParams::default();
+    for (pattern_segment, path_segment) in pattern_segments.iter().zip(path_segments) {
+        let (prefix, rest) = match pattern_segment.split_once('{') {
+            Some(split) => split,
+            None if *pattern_segment == path_segment => continue,
+            None => return None,
+        };
+        let (name, suffix) = rest.split_once('}')?;
+        let value = path_segment.strip_prefix(prefix)?.strip_suffix(suffix)?;
+        if value.is_empty() {
+            return None;
+        }
+        params.0.insert(name.to_string(), value.to_string());
+    }
+    Some(params)
+}
+
+/// Request matched to a route
+pub(crate) struct Route<'a> {
+    /// Account signed in to the web4 gateway
+    pub account_id: Option<&'a AccountId>,
+    pub params: PathParams,
+    pub query: QueryParams,
+}
+
+type RouteHandler = fn(&Contract, &Route) -> Option<Web4Response>;
+
+/// Pages in the order they are matched, a handler returns `None` when the page is not found
+const ROUTES: &[(&str, RouteHandler)] = &[
+    ("/", |contract, route| {
+        Some(contract.dashboard_page(route.account_id, &TasksQuery::default()))
+    }),
+    ("/robots.txt", |_, _| {
+        Some(Web4Response::plain_response("User-agent: *\nDisallow:".to_string()))
+    }),
+    ("/add-task", |_, _| Some(form_page(include_str!("../res/add-task.html")))),
+    ("/complete", |_, _| Some(form_page(include_str!("../res/complete.html")))),
+    ("/leaderboard", |contract, _| Some(contract.leaderboard_page())),
+    ("/nft/{id}.svg", |contract, route| contract.achievement_image(route.params.get("id")?)),
+    ("/task/{id}", |contract, route| contract.task_page(route.account_id?, route.params.get("id")?)),
+    ("/u/{account}", |contract, route| {
+        Some(contract.dashboard_page(Some(&route.params.get("account")?), &TasksQuery::default()))
+    }),
+    ("/u/{account}/tasks", |contract, route| {
+        let query = TasksQuery::parse(&route.query).ok()?;
+        Some(contract.dashboard_page(Some(&route.params.get("account")?), &query))
+    }),
+    ("/u/{account}/calendar.ics", |contract, route| {
+        Some(contract.calendar_feed(&route.params.get("account")?))
+    }),
+    ("/u/{account}/task/{id}", |contract, route| {
+        contract.task_page(&route.params.get("account")?, route.params.get("id")?)
+    }),
+    ("/api/stats.json", |contract, _| Some(contract.api_stats())),
+    ("/api/users/{account}/stats.json", |contract, route| {
+        Some(contract.api_user_stats(route.params.get("account")?))
+    }),
+    ("/api/users/{account}/tasks.json", |contract, route| {
+        let account_id = route.params.get("account")?;
+        Some(match TasksQuery::parse(&route.query) {
+            Ok(query) => contract.api_user_tasks(account_id, &query),
+            Err(name) => Web4Response::json_error(400, &format!("Invalid parameter `{}`", name)),
+        })
+    }),
+    ("/api/tasks/{account}/{id}.json", |contract, route| {
+        contract.api_task(route.params.get("account")?, route.params.get("id")?)
+    }),
+];
+
+/// Tasks selected by the status and shown by pages of `limit` tasks
+pub(crate) struct TasksQuery {
+    pub status: TaskStatus,
+    /// Number of the page starting from 1
+    pub page: u64,
+    pub limit: u64,
+}
+
+impl Default for TasksQuery {
+    fn default() -> Self {
+        Self {
+            status: TaskStatus::All,
+            page: 1,
+            limit: TASKS_PAGE_SIZE,
+        }
+    }
+}
+
+#[derive(Default, Clone, Copy, PartialEq)]
+pub(crate) enum TaskStatus {
+    #[default]
+    All,
+    Open,
+    Completed,
+    Overdue,
+}
+
+impl FromStr for TaskStatus {
+    type Err = ();
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text {
+            "all" => Ok(TaskStatus::All),
+            "open" => Ok(TaskStatus::Open),
+            "completed" => Ok(TaskStatus::Completed),
+            "overdue" => Ok(TaskStatus::Overdue),
+            _ => Err(()),
+        }
+    }
+}
+
+impl TaskStatus {
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            TaskStatus::All => "all",
+            TaskStatus::Open => "open",
+            TaskStatus::Completed => "completed",
+            TaskStatus::Overdue => "overdue",
+        }
+    }
+
+    pub(crate) fn filter(&self) -> TaskFilter {
+        match self {
+            TaskStatus::All => TaskFilter::default(),
+            TaskStatus::Open => TaskFilter {
+                is_complete: Some(false),
+                overdue: Some(false),
+                ..TaskFilter::default()
+            },
+            TaskStatus::Completed => TaskFilter {
+                is_complete: Some(true),
+                ..TaskFilter::default()
+            },
+            TaskStatus::Overdue => TaskFilter {
+                overdue: Some(true),
+                ..TaskFilter::default()
+            },
+        }
+    }
+}
+
+impl TasksQuery {
+    /// Reads `status`, `page` and `limit` of the query, returns the name of the first
+    /// malformed parameter as the error
+    pub(crate) fn parse(query: &QueryParams) -> Result<Self, &'static str> {
+        let status = query.get("status").map_err(|_| "status")?.unwrap_or_default();
+        let page = match query.get::<u64>("page").map_err(|_| "page")? {
+            Some(0) => return Err("page"),
+            page => page.unwrap_or(1),
+        };
+        let limit = match query.get::<u64>("limit").map_err(|_| "limit")? {
+            Some(limit) if limit == 0 || limit > MAX_TASKS_PAGE_SIZE => return Err("limit"),
+            limit => limit.unwrap_or(TASKS_PAGE_SIZE),
+        };
+        Ok(Self { status, page, limit })
+    }
+
+    /// Index of the first task of the page among the selected tasks
+    pub(crate) fn first_index(&self) -> u64 {
+        (self.page - 1) * self.limit
+    }
+}
+
+/// Renders a page with a form that sends transactions to the contract
+fn form_page(template: &str) -> Web4Response {
+    Web4Response::html_response(render(
+        template,
+        &page_scope()
+            .text("contract_id", env::current_account_id())
+            .text("network", "testnet"),
+    ))
+}
+
+/// Variables shared by all pages
+fn page_scope() -> Scope {
+    Scope::new().html("stylesheet", STYLES_BODY)
+}
+
+impl Contract {
+    /// Renders the page of the task of the account
+    fn task_page(&self, account_id: &AccountId, record_id: i64) -> Option<Web4Response> {
+        let record = self.get_user_records(account_id)?.get_record(record_id)?;
+
+        let proofs = record
+            .proofs
+            .iter()
+            .map(|proof| {
+                Scope::new()
+                    .text("uri", &proof.uri)
+                    .text("hash", &proof.hash)
+                    .text("submitted_at", proof.submitted_at)
+            })
+            .collect();
+        let verifier = record
+            .verifier
+            .as_ref()
+            .map_or("".to_string(), |verifier| verifier.to_string());
+
+        Some(Web4Response::html_response(render(
+            include_str!("../res/task.html"),
+            &page_scope()
+                .text("account_id", account_id)
+                .text("record_id", record_id)
+                .text("task", &record.task)
+                .text("is_complete_status", record.is_complete_status)
+                .text("deposit_status", format!("{:?}", record.deposit_status))
+                .text("guarantee", record.guarantee_of_task_completion)
+                .text("deadline_time", record.deadline_time)
+                .text("verifier", verifier)
+                .list("proofs", proofs),
+        )))
+    }
+
+    /// Renders tasks and stats of the account, the signed-in user sees their own dashboard on `/`
+    /// and anyone can open the dashboard of any user on `/u/{account}`
+    fn dashboard_page(&self, account_id: Option<&AccountId>, query: &TasksQuery) -> Web4Response {
+        let mut scope = page_scope()
+            .flag("has_account", account_id.is_some())
+            .flag("is_unfiltered", query.status == TaskStatus::All && query.page == 1)
+            .text("status", query.status.as_str())
+            .text("limit", query.limit);
+        let mut stats = UserStats::default();
+
+        if let Some(account_id) = account_id {
+            let filter = query.status.filter();
+            let tasks = self
+                .get_user_tasks(
+                    account_id.clone(),
+                    Some(query.first_index()),
+                    Some(query.limit),
+                    Some(filter.clone()),
+                )
+                .into_iter()
+                .map(|(record_id, record)| {
+                    Scope::new()
+                        .text("record_id", record_id)
+                        .text("task", record.task)
+                        .text("is_complete_status", record.is_complete_status)
+                        .text("guarantee", record.guarantee_of_task_completion)
+                        .text("deadline_time", record.deadline_time)
+                })
+                .collect();
+            let task_count = self.get_user_task_count(account_id.clone(), Some(filter));
+
+            scope = scope.text("account_id", account_id).list("tasks", tasks);
+            if query.page > 1 {
+                scope = scope.text("previous_page", query.page - 1);
+            }
+            if query.page * query.limit < task_count {
+                scope = scope.text("next_page", query.page + 1);
+            }
+            stats = self.get_user_stats(account_id.clone());
+        }
+
+        let stats = [
+            ("Tasks created", stats.tasks_created.to_string()),
+            ("Completed on time", stats.completed_on_time.to_string()),
+            ("Completed late", stats.completed_late.to_string()),
+            ("Deposits withheld", stats.withheld.to_string()),
+            ("Total staked", stats.total_staked.0.to_string()),
+            ("Total refunded", stats.total_refunded.0.to_string()),
+            ("Total forfeited", stats.total_forfeited.0.to_string()),
+            ("Current streak", stats.current_streak.to_string()),
+            ("Longest streak", stats.longest_streak.to_string()),
+        ]
+        .iter()
+        .map(|(name, value)| Scope::new().text("name", name).text("value", value))
+        .collect();
+
+        Web4Response::html_response(render(
+            include_str!("../res/index.html"),
+            &scope.list("stats", stats),
+        ))
+    }
+
+    /// Renders the image of the achievement token
+    fn achievement_image(&self, token_id: String) -> Option<Web4Response> {
+        let token = self.nft_token(token_id)?;
+        let title = token
+            .metadata
+            .and_then(|metadata| metadata.title)
+            .unwrap_or_default();
+        Some(Web4Response::svg_response(achievement_svg(&title, &token.owner_id)))
+    }
+
+    /// Renders the best users by every metric of the leaderboard
+    fn leaderboard_page(&self) -> Web4Response {
+        let rows = |metric: LeaderboardMetric| {
+            self.get_leaderboard(metric, None)
+                .iter()
+                .enumerate()
+                .map(|(index, entry)| {
+                    let score = match metric {
+                        LeaderboardMetric::OnTimeRate => format!("{:.2}", entry.score.0 as f64 / 100.0),
+                        _ => entry.score.0.to_string(),
+                    };
+                    Scope::new()
+                        .text("rank", index + 1)
+                        .text("account_id", &entry.account_id)
+                        .text("score", score)
+                })
+                .collect()
+        };
+
+        Web4Response::html_response(render(
+            include_str!("../res/leaderboard.html"),
+            &page_scope()
+                .list("on_time_rate", rows(LeaderboardMetric::OnTimeRate))
+                .list("total_staked", rows(LeaderboardMetric::TotalStaked))
+                .list("total_recovered", rows(LeaderboardMetric::TotalRecovered)),
+        ))
+    }
+}
+
 #[near_bindgen]
 impl Contract {
-    #[allow(unused_variables)]
     pub fn web4_get(&self, request: Web4Request) -> Web4Response {
-        let path = request.path;
-
-        if path == "/robots.txt" {
-            return Web4Response::plain_response("User-agent: *\nDisallow:".to_string());
-        }
-
-        if path == "/add-task" {
-            return Web4Response::html_response(
-                include_str!("../res/add-task.html")
-                    .replace("%STYLESHEET%", &STYLES_BODY)
-                    .replace("%CONTRACT_ID%", &env::current_account_id().to_string())
-                    .replace("%NETWORK%", "testnet"),
-            );
-        }
-
-        if path == "/complete" {
-            return Web4Response::html_response(
-                include_str!("../res/complete.html")
-                    .replace("%STYLESHEET%", &STYLES_BODY)
-                    .replace("%CONTRACT_ID%", &env::current_account_id().to_string())
-                    .replace("%NETWORK%", "testnet"),
-            );
-        }
-
-        let mut app_html = "".to_string();
-        let user_id = AccountId::new_unchecked("lrn.testnet".to_string()); // change to your contract, will be corrected in the future
-
-        for (record_id, user_records) in self.get_all_user_tasks(user_id) {
-            app_html = format!(
-                "{}<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>",
-                &app_html,
-                record_id.to_string(),
-                user_records.task,
-                user_records.is_complete_status,
-                user_records.guarantee_of_task_completion,
-                user_records.deadline_time
-            );
-        }
-
-        Web4Response::html_response(
-            include_str!("../res/index.html")
-                .replace("%STYLESHEET%", &STYLES_BODY)
-                .replace("%USER_RECORDS%", &app_html)
-                .replace("%CONTRACT_ID%", &env::current_account_id().to_string())
-                .replace("%NETWORK%", "testnet"),
-        )
+        let (path, query_string) = match request.path.split_once('?') {
+            Some((path, query_string)) => (path, Some(query_string)),
+            None => (request.path.as_str(), None),
+        };
+
+        // Clients of the API get errors in JSON
+        let not_found = || match path.starts_with("/api/") {
+            true => Web4Response::json_error(404, "Not found"),
+            false => Web4Response::not_found(),
+        };
+
+        for (pattern, handler) in ROUTES {
+            if let Some(params) = match_route(pattern, path) {
+                let route = Route {
+                    account_id: request.account_id.as_ref(),
+                    params,
+                    query: QueryParams::new(&request, query_string),
+                };
+                return handler(self, &route).unwrap_or_else(not_found);
+            }
+        }
+        not_found()
     }
 }
@@ -2,11 +2,8 @@ use near_units::parse_near;
 use workspaces::prelude::*;
 use serde_json::json;
 use workspaces::{Account, Contract, Worker, network::Sandbox};
-use near_sdk::{Timestamp};
 
 const WASM_FILEPATH: &str = "../../out/main.wasm";
-const TIMESTAMP_IN_FUTURE_MS: Timestamp = 1693818395000;
-const TIMESTAMP_IN_PAST_MS: Timestamp = 1630746395000;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
@@ -17,6 +14,33 @@ async fn main() -> anyhow::Result<()> {
     // create accounts
     let owner = worker.root_account();
 
+    contract
+        .call(&worker, "new")
+        .args_json(json!({
+            "owner_id": owner.id(),
+            "config": {
+                "min_deposit": parse_near!("3 N").to_string(),
+                "max_deposit": parse_near!("1000 N").to_string(),
+                "max_task_length": 1000,
+                "max_deadline_horizon": "31536000000000000",
+                "keeper_fee_bps": 100,
+                "extension_policy": "Penalty",
+                "extension_fee_bps": 1000,
+                "cancel_cooldown": "86400000000000",
+                "cancel_fee_bps": 500,
+                "token_min_deposits": {},
+                "token_max_deposits": {},
+                "verification_timeout": "604800000000000",
+                "verification_default": "Approve",
+                "charities": [],
+                "beneficiary_whitelist_only": false,
+                "platform_fee_bps": 500,
+                "leaderboard_min_tasks": 5
+            }
+        }))?
+        .transact()
+        .await?;
+
     let alice = owner
         .create_subaccount(&worker, "alice")
         .initial_balance(parse_near!("30 N"))
@@ -44,13 +68,13 @@ async fn test_create_task(
 
     alice.call(&worker, &contract.id(), "create_task")
         .deposit(parse_near!("3 N"))
-        .args_json(json!({"task": "First task", "deadline_time": 1693818395}))?
+        .args_json(json!({"task": "First task", "deadline_time": {"relative": "3d"}}))?
         .transact()
         .await?;
 
     alice.call(&worker, &contract.id(), "create_task")
         .deposit(parse_near!("4 N"))
-        .args_json(json!({"task": "Second task", "deadline_time": 1630746395}))?
+        .args_json(json!({"task": "Second task", "deadline_time": {"relative": "2w"}}))?
         .transact()
         .await?;
 
@@ -58,7 +82,7 @@ async fn test_create_task(
         .await?
         .balance;
 
-    assert_eq!(started_balance, balance_after_create_two_tasks - parse_near!("7 N"));
+    assert!(started_balance - balance_after_create_two_tasks >= parse_near!("7 N"));
 
     println!("      Passed ✅ create 2 tasks");
     Ok(())
@@ -81,30 +105,14 @@ async fn test_getting_tasks(
         .await?
         .json()?;
 
-    let first_task_expected = json!(
-        {
-            "task": "First task",
-            "is_complete_status": false,
-            "guarantee_of_task_completion": 3e+24,
-            "deadline_time": 1693818395,
-            "account_balance": 33e+26,
-            "deposit_status": "Contributed"
-        }
-    );
-
-    let second_task_expected = json!(
-        {
-            "task": "Second task",
-            "is_complete_status": false,
-            "guarantee_of_task_completion": 3e+24,
-            "deadline_time": 1630746395,
-            "account_balance": 37e+26,
-            "deposit_status": "Contributed"
-        }
-    );
-
-    assert_eq!(first_task, first_task_expected);
-    assert_eq!(second_task, second_task_expected);
+    assert_eq!(first_task["task"], "First task");
+    assert_eq!(first_task["is_complete_status"], false);
+    assert_eq!(first_task["guarantee_of_task_completion"], json!(3e+24));
+    assert_eq!(first_task["deposit_status"], "Contributed");
+
+    assert_eq!(second_task["task"], "Second task");
+    assert_eq!(second_task["guarantee_of_task_completion"], json!(4e+24));
+    assert!(second_task["deadline_time"].as_u64() > first_task["deadline_time"].as_u64());
 
     println!("      Passed ✅ getting 2 tasks");
     Ok(())
@@ -115,43 +123,33 @@ async fn test_completing_tasks(
     contract: &Contract,
     worker: &Worker<Sandbox>,
 ) -> anyhow::Result<()> {
-    let complete_first_task: serde_json::Value = alice.call(&worker, contract.id(), "make_complete_task_status")
+    let complete_first_task: String = alice.call(&worker, contract.id(), "make_complete_task_status")
         .args_json(json!({"changed_record_id": 1}))?
         .transact()
         .await?
         .json()?;
 
-    let complete_second_task: serde_json::Value = alice.call(&worker, contract.id(), "make_complete_task_status")
-        .args_json(json!({"changed_record_id": 2}))?
+    assert_eq!(complete_first_task, "Deposit refunded 3000000000000000000000000");
+
+    let claimable: String = alice.call(&worker, contract.id(), "get_claimable")
+        .args_json(json!({"account_id": alice.id()}))?
         .transact()
         .await?
         .json()?;
 
-    let complete_first_task_expected = json!(
-        {
-            "task": "First task",
-            "is_complete_status": true,
-            "guarantee_of_task_completion": 3e+24,
-            "deadline_time": 1630746395,
-            "account_balance": 33e+26,
-            "deposit_status": "Refunded"
-        }
-    );
-
-    let complete_second_task_expected = json!(
-        {
-            "task": "Second task",
-            "is_complete_status": true,
-            "guarantee_of_task_completion": 3e+24,
-            "deadline_time": 1693818395,
-            "account_balance": 33e+26,
-            "deposit_status": "Withheld"
-        }
-    );
-
-    assert_eq!(complete_first_task, complete_first_task_expected);
-    assert_eq!(complete_second_task, complete_second_task_expected);
-
-    println!("      Passed ✅ completing 2 tasks");
+    assert_eq!(claimable, parse_near!("3 N").to_string());
+
+    let balance_before_claim = alice.view_account(&worker).await?.balance;
+
+    alice.call(&worker, contract.id(), "claim_refunds")
+        .args_json(json!({}))?
+        .transact()
+        .await?;
+
+    let balance_after_claim = alice.view_account(&worker).await?.balance;
+
+    assert!(balance_after_claim > balance_before_claim + parse_near!("2.9 N"));
+
+    println!("      Passed ✅ completing task and claiming refund");
     Ok(())
 }